@@ -0,0 +1,271 @@
+use crate::{NodeColor, RbError, RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+use std::ptr::{null, null_mut};
+
+/// Maps an [`RbLink`] field to the struct holding it, like Linux
+/// `container_of`, and picks the key that struct is ordered by.
+///
+/// # Safety
+///
+/// `OFFSET` must be the offset of an `RbLink<Self>` field inside `Value`,
+/// as given by `std::mem::offset_of!`.
+pub unsafe trait RbAdapter: Sized {
+    type Key: PartialOrd;
+    type Value;
+    const OFFSET: usize;
+
+    fn key(value: &Self::Value) -> Self::Key;
+}
+
+// the value of a link node, pointing back at the struct around the link.
+// It is set as the link joins a tree, from a pointer covering the whole
+// struct, since one derived from the link alone may not reach past it
+pub(crate) struct Linked<Ad: RbAdapter> {
+    value: *const Ad::Value,
+}
+
+type LinkNode<Ad> = RbNode<<Ad as RbAdapter>::Key, Linked<Ad>>;
+
+impl<Ad: RbAdapter> Linked<Ad> {
+    fn container(&self) -> &Ad::Value {
+        unsafe { &*self.value }
+    }
+}
+
+impl<Ad: RbAdapter> RbTrait<Ad::Key> for Linked<Ad> {
+    fn new(_: Ad::Key) -> Self {
+        Linked { value: null() }
+    }
+
+    fn get(&self) -> Ad::Key {
+        Ad::key(self.container())
+    }
+
+    // the key belongs to the containing struct
+    fn set(&mut self, _: Ad::Key) {}
+}
+
+impl<Ad: RbAdapter> PartialEq for Linked<Ad> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<Ad: RbAdapter> PartialOrd for Linked<Ad> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.get().partial_cmp(&other.get())
+    }
+}
+
+/// The links of one tree, embedded as a field of the struct it indexes. A
+/// struct with several links can sit in several trees at once.
+#[repr(transparent)]
+pub struct RbLink<Ad: RbAdapter> {
+    node: LinkNode<Ad>,
+}
+
+impl<Ad: RbAdapter> RbLink<Ad> {
+    pub fn new() -> Self {
+        RbLink {
+            node: RbNode {
+                color: NodeColor::Red,
+                val: Linked { value: null() },
+                parent: null_mut(),
+                childs: [null_mut(), null_mut()],
+            },
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.node.is_linked()
+    }
+
+    /// Marks the link unlinked, see [`RbNode::reset`].
+    ///
+    /// # Safety
+    ///
+    /// No live tree may still hold the link.
+    pub unsafe fn reset(&mut self) {
+        self.node.clear_links();
+    }
+}
+
+impl<Ad: RbAdapter> Default for RbLink<Ad> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A tree of existing structs, linked through their `RbLink<Ad>` field and
+/// ordered by `Ad::key`.
+pub struct RbAdapterTree<Ad: RbAdapter> {
+    tree: RbTree<Ad::Key, Linked<Ad>>,
+}
+
+impl<Ad: RbAdapter> RbAdapterTree<Ad> {
+    pub fn new() -> Self {
+        RbAdapterTree {
+            tree: RbTree::new(),
+        }
+    }
+
+    // the link node inside value, keeping the reach of value itself so the
+    // tree can get back to the whole struct
+    fn link(value: *mut Ad::Value) -> *mut LinkNode<Ad> {
+        unsafe { value.byte_add(Ad::OFFSET).cast::<LinkNode<Ad>>() }
+    }
+
+    pub fn insert(&mut self, value: &mut Ad::Value) -> Result<&mut Self, RbError> {
+        let value: *mut Ad::Value = value;
+        let node = Self::link(value);
+        unsafe {
+            if (*node).is_linked() {
+                return Err(RbError::AlreadyLinked);
+            }
+            (*node).val.value = value;
+            self.tree.insert_node(node);
+        }
+        Ok(self)
+    }
+
+    pub fn delete(&mut self, value: &mut Ad::Value) -> Result<&mut Self, RbError> {
+        self.tree.delete(unsafe { &mut *Self::link(value) })?;
+        Ok(self)
+    }
+
+    pub fn find(&self, key: &Ad::Key) -> Option<&Ad::Value> {
+        self.tree.find(key).map(|node| node.val.container())
+    }
+
+    pub fn contains(&self, key: &Ad::Key) -> bool {
+        self.tree.contains(key)
+    }
+
+    pub fn first(&self) -> Option<&Ad::Value> {
+        self.tree.first().map(|node| node.val.container())
+    }
+
+    pub fn last(&self) -> Option<&Ad::Value> {
+        self.tree.last().map(|node| node.val.container())
+    }
+
+    /// Unlinks every struct, leaving the tree empty.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    pub fn iter(&self) -> Iter<'_, Ad> {
+        Iter {
+            inner: self.tree.iter(),
+        }
+    }
+}
+
+impl<Ad: RbAdapter> Default for RbAdapterTree<Ad> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, Ad: RbAdapter> {
+    inner: crate::Iter<'a, Ad::Key, Linked<Ad>>,
+}
+
+impl<'a, Ad: RbAdapter> Iterator for Iter<'a, Ad> {
+    type Item = &'a Ad::Value;
+
+    fn next(&mut self) -> Option<&'a Ad::Value> {
+        self.inner.next().map(|val| val.container())
+    }
+}
+
+impl<'a, Ad: RbAdapter> DoubleEndedIterator for Iter<'a, Ad> {
+    fn next_back(&mut self) -> Option<&'a Ad::Value> {
+        self.inner.next_back().map(|val| val.container())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use std::mem::offset_of;
+
+    struct Conn {
+        id: u32,
+        deadline: u64,
+        by_id: RbLink<ById>,
+        by_deadline: RbLink<ByDeadline>,
+    }
+
+    struct ById;
+
+    unsafe impl RbAdapter for ById {
+        type Key = u32;
+        type Value = Conn;
+        const OFFSET: usize = offset_of!(Conn, by_id);
+
+        fn key(conn: &Conn) -> u32 {
+            conn.id
+        }
+    }
+
+    struct ByDeadline;
+
+    unsafe impl RbAdapter for ByDeadline {
+        type Key = u64;
+        type Value = Conn;
+        const OFFSET: usize = offset_of!(Conn, by_deadline);
+
+        fn key(conn: &Conn) -> u64 {
+            conn.deadline
+        }
+    }
+
+    #[test]
+    fn two_trees() {
+        let mut array = [0u32; 100];
+        let _ = utils::read_blocks_from_file::<u32>("/dev/urandom", &mut array, 100);
+        let mut conns: Vec<Conn> = array
+            .iter()
+            .enumerate()
+            .map(|(id, x)| Conn {
+                id: id as u32,
+                deadline: (*x % 1000) as u64,
+                by_id: RbLink::new(),
+                by_deadline: RbLink::new(),
+            })
+            .collect();
+
+        let mut by_id = RbAdapterTree::<ById>::new();
+        let mut by_deadline = RbAdapterTree::<ByDeadline>::new();
+        for conn in conns.iter_mut() {
+            by_id.insert(conn).unwrap();
+            by_deadline.insert(conn).unwrap();
+        }
+
+        assert!(by_id.iter().map(|c| c.id).eq(0..100));
+        let deadlines: Vec<u64> = by_deadline.iter().map(|c| c.deadline).collect();
+        assert!(deadlines.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(
+            by_deadline.first().map(|c| c.deadline),
+            deadlines.first().copied()
+        );
+
+        // leaving one tree keeps the struct in the other
+        let soonest = by_deadline.first().unwrap().id as usize;
+        by_deadline.delete(&mut conns[soonest]).unwrap();
+        assert_eq!(by_deadline.iter().count(), 99);
+        assert_eq!(
+            by_id.find(&(soonest as u32)).map(|c| c.id),
+            Some(soonest as u32)
+        );
+        assert_eq!(by_id.last().map(|c| c.id), Some(99));
+        assert!(!by_id.contains(&100));
+
+        by_id.clear();
+        assert!(by_id.first().is_none());
+        assert!(conns.iter().all(|c| !c.by_id.is_linked()));
+        by_id.insert(&mut conns[0]).unwrap();
+    }
+}
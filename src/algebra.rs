@@ -0,0 +1,451 @@
+use crate::join::{Parts, Subtree};
+use crate::{Iter, RbAugment, RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::ptr::null_mut;
+
+// both in-order walks side by side
+struct Merge<'a, Key, T: RbTrait<Key> + PartialOrd> {
+    a: Peekable<Iter<'a, Key, T>>,
+    b: Peekable<Iter<'a, Key, T>>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Merge<'a, Key, T> {
+    // the smaller next value tagged with its side, both when they are equal
+    fn next(&mut self) -> (Option<&'a T>, Option<&'a T>) {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if a < b => (self.a.next(), None),
+            (Some(a), Some(b)) if b < a => (None, self.b.next()),
+            _ => (self.a.next(), self.b.next()),
+        }
+    }
+}
+
+/// Values in either tree, equal values taken once from the first. Equal
+/// values are paired up one to one, so on duplicates every operation counts
+/// like a multiset one.
+pub struct Union<'a, Key, T: RbTrait<Key> + PartialOrd>(Merge<'a, Key, T>);
+
+/// Values in both trees, taken from the first.
+pub struct Intersection<'a, Key, T: RbTrait<Key> + PartialOrd>(Merge<'a, Key, T>);
+
+/// Values in the first tree but not in the second.
+pub struct Difference<'a, Key, T: RbTrait<Key> + PartialOrd>(Merge<'a, Key, T>);
+
+/// Values in exactly one of the trees.
+pub struct SymmetricDifference<'a, Key, T: RbTrait<Key> + PartialOrd>(Merge<'a, Key, T>);
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for Union<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match self.0.next() {
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for Intersection<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.0.next() {
+                (Some(a), Some(_)) => return Some(a),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for Difference<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.0.next() {
+                (Some(a), None) => return Some(a),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for SymmetricDifference<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.0.next() {
+                (Some(a), None) => return Some(a),
+                (None, Some(b)) => return Some(b),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+type SetOp<Key, T, F> = fn(Subtree<Key, T>, Subtree<Key, T>, &mut F) -> Subtree<Key, T>;
+
+// the in place operations below recurse on the root of one tree and split
+// the other one by it, as in "Just Join for Parallel Ordered Sets"; a split
+// finds one equal node only, hence their precondition of unique values on
+// each side. Subtrees carry their black heights so
+// each join and split costs what the paper charges for it
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    fn merge<'a>(&'a self, other: &'a Self) -> Merge<'a, Key, T> {
+        Merge {
+            a: self.iter().peekable(),
+            b: other.iter().peekable(),
+        }
+    }
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, Key, T> {
+        Union(self.merge(other))
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, Key, T> {
+        Intersection(self.merge(other))
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, Key, T> {
+        Difference(self.merge(other))
+    }
+
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, Key, T> {
+        SymmetricDifference(self.merge(other))
+    }
+
+    // splits tree by the value of pivot
+    fn split_at(tree: Subtree<Key, T>, pivot: *mut RbNode<Key, T>) -> Parts<Key, T> {
+        Self::split_raw(tree, &mut |val: &T| {
+            let pivot = unsafe { &(*pivot).val };
+            if val < pivot {
+                Ordering::Less
+            } else if pivot < val {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+    }
+
+    fn drop_node<F: FnMut(&mut RbNode<Key, T>)>(node: &mut RbNode<Key, T>, dropped: &mut F) {
+        node.clear_links();
+        dropped(node);
+    }
+
+    fn drop_all<F: FnMut(&mut RbNode<Key, T>)>(root: *mut RbNode<Key, T>, dropped: &mut F) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if let Some(node) = unsafe { node.as_mut() } {
+                stack.extend(node.childs);
+                Self::drop_node(node, dropped);
+            }
+        }
+    }
+
+    fn union_raw<F: FnMut(&mut RbNode<Key, T>)>(
+        a: Subtree<Key, T>,
+        b: Subtree<Key, T>,
+        dropped: &mut F,
+    ) -> Subtree<Key, T> {
+        if a.0.is_null() {
+            return b;
+        }
+        if b.0.is_null() {
+            return a;
+        }
+
+        let (al, ar) = Self::children(a);
+        let (bl, eq, br) = Self::split_at(b, a.0);
+        let left = Self::union_raw(al, bl, dropped);
+        let right = Self::union_raw(ar, br, dropped);
+        if let Some(eq) = unsafe { eq.as_mut() } {
+            Self::drop_node(eq, dropped);
+        }
+        Self::join_raw(left, a.0, right)
+    }
+
+    fn intersection_raw<F: FnMut(&mut RbNode<Key, T>)>(
+        a: Subtree<Key, T>,
+        b: Subtree<Key, T>,
+        dropped: &mut F,
+    ) -> Subtree<Key, T> {
+        if a.0.is_null() || b.0.is_null() {
+            Self::drop_all(a.0, dropped);
+            Self::drop_all(b.0, dropped);
+            return (null_mut(), 0);
+        }
+
+        let (al, ar) = Self::children(a);
+        let (bl, eq, br) = Self::split_at(b, a.0);
+        let left = Self::intersection_raw(al, bl, dropped);
+        let right = Self::intersection_raw(ar, br, dropped);
+        match unsafe { eq.as_mut() } {
+            Some(eq) => {
+                Self::drop_node(eq, dropped);
+                Self::join_raw(left, a.0, right)
+            }
+            None => {
+                Self::drop_node(unsafe { &mut *a.0 }, dropped);
+                Self::join2_raw(left, right)
+            }
+        }
+    }
+
+    fn difference_raw<F: FnMut(&mut RbNode<Key, T>)>(
+        a: Subtree<Key, T>,
+        b: Subtree<Key, T>,
+        dropped: &mut F,
+    ) -> Subtree<Key, T> {
+        if a.0.is_null() || b.0.is_null() {
+            Self::drop_all(b.0, dropped);
+            return a;
+        }
+
+        let (bl, br) = Self::children(b);
+        let (al, eq, ar) = Self::split_at(a, b.0);
+        let left = Self::difference_raw(al, bl, dropped);
+        let right = Self::difference_raw(ar, br, dropped);
+        Self::drop_node(unsafe { &mut *b.0 }, dropped);
+        if let Some(eq) = unsafe { eq.as_mut() } {
+            Self::drop_node(eq, dropped);
+        }
+        Self::join2_raw(left, right)
+    }
+
+    fn symmetric_difference_raw<F: FnMut(&mut RbNode<Key, T>)>(
+        a: Subtree<Key, T>,
+        b: Subtree<Key, T>,
+        dropped: &mut F,
+    ) -> Subtree<Key, T> {
+        if a.0.is_null() {
+            return b;
+        }
+        if b.0.is_null() {
+            return a;
+        }
+
+        let (al, ar) = Self::children(a);
+        let (bl, eq, br) = Self::split_at(b, a.0);
+        let left = Self::symmetric_difference_raw(al, bl, dropped);
+        let right = Self::symmetric_difference_raw(ar, br, dropped);
+        match unsafe { eq.as_mut() } {
+            Some(eq) => {
+                Self::drop_node(eq, dropped);
+                Self::drop_node(unsafe { &mut *a.0 }, dropped);
+                Self::join2_raw(left, right)
+            }
+            None => Self::join_raw(left, a.0, right),
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        self.iter().zip(self.iter().skip(1)).all(|(a, b)| a < b)
+    }
+
+    // runs op on both roots, self keeps the result and other is left empty
+    fn combine<F: FnMut(&mut RbNode<Key, T>)>(
+        &mut self,
+        other: &mut Self,
+        mut dropped: F,
+        op: SetOp<Key, T, F>,
+    ) -> &mut Self {
+        debug_assert!(
+            self.is_unique() && other.is_unique(),
+            "in place set operations need unique values"
+        );
+        self.root = op(self.subtree(), other.subtree(), &mut dropped).0;
+        other.root = null_mut();
+
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        self
+    }
+
+    /// Moves the nodes of `other` into `self` as a set union, in
+    /// O(m log(n/m + 1)) for trees of m <= n nodes. Every node of `other`
+    /// equal to one in `self` is handed to `dropped` instead, unlinked.
+    ///
+    /// Neither tree may hold equal values twice, debug builds panic if one
+    /// does. [`RbTree::union`] takes duplicates as well.
+    pub fn union_in_place<F: FnMut(&mut RbNode<Key, T>)>(
+        &mut self,
+        other: &mut Self,
+        dropped: F,
+    ) -> &mut Self {
+        self.combine(other, dropped, Self::union_raw)
+    }
+
+    /// Keeps only the nodes of `self` equal to one in `other`, every other
+    /// node of both trees is handed to `dropped`, unlinked.
+    ///
+    /// Neither tree may hold equal values twice, debug builds panic if one
+    /// does. [`RbTree::intersection`] takes duplicates as well.
+    pub fn intersection_in_place<F: FnMut(&mut RbNode<Key, T>)>(
+        &mut self,
+        other: &mut Self,
+        dropped: F,
+    ) -> &mut Self {
+        self.combine(other, dropped, Self::intersection_raw)
+    }
+
+    /// Keeps only the nodes of `self` not equal to one in `other`, every
+    /// other node of both trees is handed to `dropped`, unlinked.
+    ///
+    /// Neither tree may hold equal values twice, debug builds panic if one
+    /// does. [`RbTree::difference`] takes duplicates as well.
+    pub fn difference_in_place<F: FnMut(&mut RbNode<Key, T>)>(
+        &mut self,
+        other: &mut Self,
+        dropped: F,
+    ) -> &mut Self {
+        self.combine(other, dropped, Self::difference_raw)
+    }
+
+    /// Keeps the nodes of both trees with no equal in the other one, the
+    /// equal pairs are handed to `dropped`, unlinked.
+    ///
+    /// Neither tree may hold equal values twice, debug builds panic if one
+    /// does. [`RbTree::symmetric_difference`] takes duplicates as well.
+    pub fn symmetric_difference_in_place<F: FnMut(&mut RbNode<Key, T>)>(
+        &mut self,
+        other: &mut Self,
+        dropped: F,
+    ) -> &mut Self {
+        self.combine(other, dropped, Self::symmetric_difference_raw)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Test;
+    use crate::utils;
+    use crate::{RbNode, RbTrait, RbTree};
+    use std::collections::BTreeSet;
+
+    type Tree = RbTree<i32, Test>;
+
+    // runs the lazy and the in place form of one operation on trees of a
+    // and b, checking both against want
+    fn check<L, P>(a: &BTreeSet<i32>, b: &BTreeSet<i32>, want: Vec<i32>, lazy: L, in_place: P)
+    where
+        L: Fn(&Tree, &Tree) -> Vec<i32>,
+        P: Fn(&mut Tree, &mut Tree, &mut Vec<i32>),
+    {
+        let mut na: Vec<RbNode<i32, Test>> = a.iter().map(|&key| RbNode::new(key)).collect();
+        let mut nb: Vec<RbNode<i32, Test>> = b.iter().map(|&key| RbNode::new(key)).collect();
+        let mut ta = Tree::new();
+        let mut tb = Tree::new();
+        for node in na.iter_mut() {
+            ta.insert(node).unwrap();
+        }
+        for node in nb.iter_mut() {
+            tb.insert(node).unwrap();
+        }
+        assert_eq!(lazy(&ta, &tb), want);
+
+        // every node ends up either in the result or dropped
+        let mut dropped = Vec::new();
+        in_place(&mut ta, &mut tb, &mut dropped);
+        let kept: Vec<i32> = ta.iter().map(|v| v.get()).collect();
+        assert_eq!(kept, want);
+        assert!(tb.first().is_none());
+
+        dropped.extend(kept);
+        dropped.sort();
+        let mut all: Vec<i32> = a.iter().chain(b.iter()).copied().collect();
+        all.sort();
+        assert_eq!(dropped, all);
+    }
+
+    #[test]
+    fn against_btreeset() {
+        let mut array = [0i32; 400];
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 400);
+        let a: BTreeSet<i32> = array[..200].iter().map(|x| x.rem_euclid(300)).collect();
+        let b: BTreeSet<i32> = array[200..].iter().map(|x| x.rem_euclid(300)).collect();
+
+        check(
+            &a,
+            &b,
+            a.union(&b).copied().collect(),
+            |x, y| x.union(y).map(|v| v.get()).collect(),
+            |x, y, d| {
+                x.union_in_place(y, |n| d.push(n.val().get()));
+            },
+        );
+        check(
+            &a,
+            &b,
+            a.intersection(&b).copied().collect(),
+            |x, y| x.intersection(y).map(|v| v.get()).collect(),
+            |x, y, d| {
+                x.intersection_in_place(y, |n| d.push(n.val().get()));
+            },
+        );
+        check(
+            &a,
+            &b,
+            a.difference(&b).copied().collect(),
+            |x, y| x.difference(y).map(|v| v.get()).collect(),
+            |x, y, d| {
+                x.difference_in_place(y, |n| d.push(n.val().get()));
+            },
+        );
+        check(
+            &a,
+            &b,
+            a.symmetric_difference(&b).copied().collect(),
+            |x, y| x.symmetric_difference(y).map(|v| v.get()).collect(),
+            |x, y, d| {
+                x.symmetric_difference_in_place(y, |n| d.push(n.val().get()));
+            },
+        );
+    }
+
+    // the lazy forms pair up equal values one to one
+    #[test]
+    fn duplicates() {
+        let a = [1, 4, 4, 4, 5, 5];
+        let b = [2, 4, 4, 5, 5];
+        let mut na: Vec<RbNode<i32, Test>> = a.iter().map(|&key| RbNode::new(key)).collect();
+        let mut nb: Vec<RbNode<i32, Test>> = b.iter().map(|&key| RbNode::new(key)).collect();
+        let mut ta = Tree::new();
+        let mut tb = Tree::new();
+        for node in na.iter_mut() {
+            ta.insert(node).unwrap();
+        }
+        for node in nb.iter_mut() {
+            tb.insert(node).unwrap();
+        }
+
+        let keys = |it: &mut dyn Iterator<Item = &Test>| it.map(|v| v.get()).collect::<Vec<_>>();
+        assert_eq!(keys(&mut ta.union(&tb)), [1, 2, 4, 4, 4, 5, 5]);
+        assert_eq!(keys(&mut ta.intersection(&tb)), [4, 4, 5, 5]);
+        assert_eq!(keys(&mut ta.difference(&tb)), [1, 4]);
+        assert_eq!(keys(&mut ta.symmetric_difference(&tb)), [1, 2, 4]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "need unique values")]
+    fn duplicates_in_place() {
+        let mut na: Vec<RbNode<i32, Test>> = [4, 4, 4].map(RbNode::new).into();
+        let mut nb: Vec<RbNode<i32, Test>> = [4, 4].map(RbNode::new).into();
+        let mut ta = Tree::new();
+        let mut tb = Tree::new();
+        for node in na.iter_mut() {
+            ta.insert(node).unwrap();
+        }
+        for node in nb.iter_mut() {
+            tb.insert(node).unwrap();
+        }
+        ta.union_in_place(&mut tb, |_| {});
+    }
+}
@@ -0,0 +1,395 @@
+use crate::rebalance::{self, RbLinks};
+use crate::{NodeColor, NodeDirection};
+
+// the index standing for no node, as a null pointer does in RbTree
+const NIL: u32 = u32::MAX;
+
+#[derive(Clone)]
+struct ArenaNode<T> {
+    color: NodeColor,
+    // None while the slot is on the free list, parent then links the next
+    // free slot
+    val: Option<T>,
+    parent: u32,
+    childs: [u32; 2],
+}
+
+/// A red black tree keeping its nodes in one `Vec` and linking them by `u32`
+/// index, so it can be cloned and sent between threads. Removed slots are
+/// reused by later inserts.
+///
+/// Inserting hands back the index of the new node, which names it until it
+/// is removed.
+#[derive(Clone)]
+pub struct ArenaRbTree<T> {
+    nodes: Vec<ArenaNode<T>>,
+    root: u32,
+    free: u32,
+    len: usize,
+}
+
+impl<T: PartialOrd> ArenaRbTree<T> {
+    pub fn new() -> Self {
+        ArenaRbTree {
+            nodes: Vec::new(),
+            root: NIL,
+            free: NIL,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: u32) -> Option<&T> {
+        self.nodes.get(index as usize)?.val.as_ref()
+    }
+
+    pub fn get_mut(&mut self, index: u32) -> Option<&mut T> {
+        self.nodes.get_mut(index as usize)?.val.as_mut()
+    }
+
+    /// Index of the first value equal to `val`.
+    pub fn find(&self, val: &T) -> Option<u32> {
+        let mut found = NIL;
+        let mut node = self.root;
+        while node != NIL {
+            let cur = self.val(node);
+            if cur < val {
+                node = self.child(node, NodeDirection::RightChild);
+            } else {
+                if cur <= val {
+                    found = node;
+                }
+                node = self.child(node, NodeDirection::LeftChild);
+            }
+        }
+        (found != NIL).then_some(found)
+    }
+
+    pub fn contains(&self, val: &T) -> bool {
+        self.find(val).is_some()
+    }
+
+    pub fn first(&self) -> Option<u32> {
+        (self.root != NIL).then(|| self.extreme(self.root, NodeDirection::LeftChild))
+    }
+
+    pub fn last(&self) -> Option<u32> {
+        (self.root != NIL).then(|| self.extreme(self.root, NodeDirection::RightChild))
+    }
+
+    /// In-order successor of the node at `index`.
+    pub fn next(&self, index: u32) -> Option<u32> {
+        self.get(index)?;
+        let node = self.neighbour(index, NodeDirection::RightChild);
+        (node != NIL).then_some(node)
+    }
+
+    /// In-order predecessor of the node at `index`.
+    pub fn prev(&self, index: u32) -> Option<u32> {
+        self.get(index)?;
+        let node = self.neighbour(index, NodeDirection::LeftChild);
+        (node != NIL).then_some(node)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            tree: self,
+            head: self.first().unwrap_or(NIL),
+            tail: self.last().unwrap_or(NIL),
+        }
+    }
+
+    /// Adds `val` after any equal values, returning the index of its node.
+    pub fn insert(&mut self, val: T) -> u32 {
+        let mut parent = NIL;
+        let mut node = self.root;
+        let mut di = NodeDirection::LeftChild;
+        while node != NIL {
+            parent = node;
+            di = NodeDirection::from(self.val(node) <= &val);
+            node = self.child(node, di);
+        }
+
+        let node = self.alloc(val, parent);
+        rebalance::link(self, parent, di, node);
+        self.len += 1;
+
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        node
+    }
+
+    /// Removes the node at `index`, handing back its value.
+    pub fn remove(&mut self, index: u32) -> Option<T> {
+        self.get(index)?;
+
+        rebalance::unlink(self, index);
+        self.len -= 1;
+        let val = self.release(index);
+
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        val
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = NIL;
+        self.free = NIL;
+        self.len = 0;
+    }
+
+    fn val(&self, node: u32) -> &T {
+        self.nodes[node as usize].val.as_ref().unwrap()
+    }
+
+    fn direction(&self, node: u32, parent: u32) -> NodeDirection {
+        NodeDirection::from(self.child(parent, NodeDirection::LeftChild) != node)
+    }
+
+    fn extreme(&self, mut node: u32, di: NodeDirection) -> u32 {
+        while self.child(node, di) != NIL {
+            node = self.child(node, di);
+        }
+        node
+    }
+
+    fn neighbour(&self, mut node: u32, di: NodeDirection) -> u32 {
+        let child = self.child(node, di);
+        if child != NIL {
+            return self.extreme(child, di.opposite());
+        }
+
+        let mut parent = self.parent(node);
+        while parent != NIL && self.direction(node, parent) == di {
+            node = parent;
+            parent = self.parent(node);
+        }
+        parent
+    }
+
+    fn alloc(&mut self, val: T, parent: u32) -> u32 {
+        let node = ArenaNode {
+            color: NodeColor::Red,
+            val: Some(val),
+            parent,
+            childs: [NIL, NIL],
+        };
+
+        if self.free != NIL {
+            let index = self.free;
+            self.free = self.nodes[index as usize].parent;
+            self.nodes[index as usize] = node;
+            index
+        } else {
+            assert!(self.nodes.len() < NIL as usize, "arena is full");
+            self.nodes.push(node);
+            (self.nodes.len() - 1) as u32
+        }
+    }
+
+    fn release(&mut self, node: u32) -> Option<T> {
+        let slot = &mut self.nodes[node as usize];
+        slot.parent = self.free;
+        slot.childs = [NIL, NIL];
+        self.free = node;
+        slot.val.take()
+    }
+
+    // black height below node, None once a property is broken
+    fn verify_properties(&self, node: u32, parent: u32) -> Option<usize> {
+        if node == NIL {
+            return Some(0);
+        }
+        if self.parent(node) != parent {
+            return None;
+        }
+
+        let left = self.child(node, NodeDirection::LeftChild);
+        let right = self.child(node, NodeDirection::RightChild);
+        let red = self.color(node) == NodeColor::Red;
+        if red && (self.color(left) == NodeColor::Red || self.color(right) == NodeColor::Red) {
+            return None;
+        }
+
+        let height = self.verify_properties(left, node)?;
+        if height != self.verify_properties(right, node)? {
+            return None;
+        }
+        Some(height + !red as usize)
+    }
+
+    pub fn verify_tree(&self) -> bool {
+        if self.color(self.root) == NodeColor::Red
+            || self.verify_properties(self.root, NIL).is_none()
+        {
+            return false;
+        }
+
+        let vals: Vec<&T> = self.iter().collect();
+        vals.len() == self.len && vals.windows(2).all(|w| w[0] <= w[1])
+    }
+}
+
+// the arena keeps no summaries, so the augment hooks stay no-ops
+impl<T> RbLinks for ArenaRbTree<T> {
+    type Node = u32;
+    const NIL: u32 = NIL;
+
+    fn root(&self) -> u32 {
+        self.root
+    }
+
+    fn set_root(&mut self, node: u32) {
+        self.root = node;
+    }
+
+    fn parent(&self, node: u32) -> u32 {
+        self.nodes[node as usize].parent
+    }
+
+    fn set_parent(&mut self, node: u32, parent: u32) {
+        self.nodes[node as usize].parent = parent;
+    }
+
+    fn child(&self, node: u32, di: NodeDirection) -> u32 {
+        self.nodes[node as usize].childs[usize::from(di)]
+    }
+
+    fn set_child(&mut self, node: u32, di: NodeDirection, child: u32) {
+        self.nodes[node as usize].childs[usize::from(di)] = child;
+    }
+
+    fn color(&self, node: u32) -> NodeColor {
+        if node == NIL {
+            NodeColor::Black
+        } else {
+            self.nodes[node as usize].color
+        }
+    }
+
+    fn set_color(&mut self, node: u32, color: NodeColor) {
+        self.nodes[node as usize].color = color;
+    }
+}
+
+impl<T: PartialOrd> Default for ArenaRbTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: PartialOrd> IntoIterator for &'a ArenaRbTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    tree: &'a ArenaRbTree<T>,
+    head: u32,
+    tail: u32,
+}
+
+impl<'a, T: PartialOrd> Iter<'a, T> {
+    // takes the node at from and moves it one step towards to
+    fn step(&mut self, di: NodeDirection) -> Option<&'a T> {
+        let (from, to) = match di {
+            NodeDirection::RightChild => (self.head, self.tail),
+            NodeDirection::LeftChild => (self.tail, self.head),
+        };
+        if from == NIL {
+            return None;
+        }
+
+        if from == to {
+            self.head = NIL;
+            self.tail = NIL;
+        } else {
+            let next = self.tree.neighbour(from, di);
+            match di {
+                NodeDirection::RightChild => self.head = next,
+                NodeDirection::LeftChild => self.tail = next,
+            }
+        }
+        Some(self.tree.val(from))
+    }
+}
+
+impl<'a, T: PartialOrd> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.step(NodeDirection::RightChild)
+    }
+}
+
+impl<'a, T: PartialOrd> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.step(NodeDirection::LeftChild)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn against_sorted_vec() {
+        let mut array = [0i32; 1000];
+        let mut tree = ArenaRbTree::<i32>::new();
+        let mut model: Vec<(i32, u32)> = Vec::new();
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 1000);
+        for x in array.iter() {
+            let val = x.rem_euclid(200);
+            if x.rem_euclid(3) == 0 && !model.is_empty() {
+                let (val, index) = model.remove(val as usize % model.len());
+                assert_eq!(tree.remove(index), Some(val));
+                assert_eq!(tree.get(index), None);
+            } else {
+                model.push((val, tree.insert(val)));
+            }
+            assert_eq!(tree.len(), model.len());
+        }
+
+        // freed slots were taken again
+        assert!(tree.nodes.len() < 1000);
+
+        let mut sorted: Vec<i32> = model.iter().map(|(val, _)| *val).collect();
+        sorted.sort();
+        assert!(tree.iter().eq(sorted.iter()));
+        assert!(tree.iter().rev().eq(sorted.iter().rev()));
+        for val in 0..200 {
+            assert_eq!(tree.contains(&val), sorted.contains(&val));
+            if let Some(index) = tree.find(&val) {
+                assert_eq!(tree.get(index), Some(&val));
+                assert!(tree.prev(index).is_none_or(|p| tree.get(p) < Some(&val)));
+            }
+        }
+
+        // a clone is a separate tree
+        let copy = tree.clone();
+        let first = tree.first().unwrap();
+        tree.remove(first);
+        assert_eq!(copy.len(), tree.len() + 1);
+        assert!(copy.iter().eq(sorted.iter()));
+        assert!(copy.verify_tree());
+
+        let sent = std::thread::spawn(move || copy.iter().count())
+            .join()
+            .unwrap();
+        assert_eq!(sent, sorted.len());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{NoAugment, NodeDirection, RbAugment, RbError, RbNode, RbTrait, RbTree};
+use std::ops::Deref;
+use std::ptr::null_mut;
+
+/// An [`RbTree`] which also keeps its leftmost node, like Linux
+/// `rb_root_cached`, so the minimum is found in O(1).
+pub struct RbTreeCached<Key, T, A = NoAugment> {
+    tree: RbTree<Key, T, A>,
+    leftmost: *mut RbNode<Key, T>,
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd> RbTreeCached<Key, T> {
+    pub fn new() -> Self {
+        Self::new_augmented()
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTreeCached<Key, T, A> {
+    pub fn new_augmented() -> Self {
+        RbTreeCached {
+            tree: RbTree::new_augmented(),
+            leftmost: null_mut(),
+        }
+    }
+
+    pub fn insert(&mut self, node: &mut RbNode<Key, T>) -> Result<&mut Self, RbError> {
+        // equal values go after the ones present, so only a strictly
+        // smaller one becomes the new leftmost
+        let leftmost = self.leftmost.is_null() || unsafe { node.val < (*self.leftmost).val };
+        self.tree.insert(node)?;
+        if leftmost {
+            self.leftmost = node;
+        }
+        Ok(self)
+    }
+
+    pub fn delete(&mut self, node: &mut RbNode<Key, T>) -> Result<&mut Self, RbError> {
+        self.tree.check_member(node)?;
+        self.unlink(node);
+        Ok(self)
+    }
+
+    fn unlink(&mut self, node: &mut RbNode<Key, T>) {
+        if std::ptr::eq(self.leftmost, node) {
+            self.leftmost = RbNode::neighbour(node, NodeDirection::RightChild);
+        }
+        self.tree.unlink(node);
+    }
+
+    pub fn first(&self) -> Option<&RbNode<Key, T>> {
+        unsafe { self.leftmost.as_ref() }
+    }
+
+    /// Unlinks every node, leaving the tree empty.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.leftmost = null_mut();
+    }
+
+    /// Unlinks the leftmost node and hands it back.
+    pub fn pop_first(&mut self) -> Option<&mut RbNode<Key, T>> {
+        let node = self.leftmost;
+        if node.is_null() {
+            return None;
+        }
+
+        unsafe {
+            self.unlink(&mut *node);
+            Some(&mut *node)
+        }
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> Default for RbTreeCached<Key, T, A> {
+    fn default() -> Self {
+        Self::new_augmented()
+    }
+}
+
+// only shared access is handed out, changes have to go through the methods
+// above to keep the cached node right
+impl<Key, T, A> Deref for RbTreeCached<Key, T, A> {
+    type Target = RbTree<Key, T, A>;
+
+    fn deref(&self) -> &RbTree<Key, T, A> {
+        &self.tree
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::Test;
+    use crate::utils;
+
+    #[test]
+    fn leftmost() {
+        let mut array = [0i32; 300];
+        let mut tree = RbTreeCached::<i32, Test>::new();
+        let mut nodes: [RbNode<i32, Test>; 300] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+
+        assert!(tree.first().is_none());
+        assert!(tree.pop_first().is_none());
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 300);
+        let mut model = Vec::new();
+        for (node, key) in nodes.iter_mut().zip(array.iter()) {
+            let key = key.rem_euclid(100);
+            node.set(key);
+            tree.insert(node).unwrap();
+            model.push(key);
+            assert_eq!(
+                tree.first().map(|n| n.val().get()),
+                model.iter().min().copied()
+            );
+        }
+
+        for node in nodes.iter_mut().step_by(4) {
+            let key = node.val().get();
+            tree.delete(node).unwrap();
+            let pos = model.iter().position(|&x| x == key).unwrap();
+            model.swap_remove(pos);
+            assert_eq!(
+                tree.first().map(|n| n.val().get()),
+                model.iter().min().copied()
+            );
+        }
+
+        model.sort();
+        for key in model {
+            assert_eq!(tree.pop_first().unwrap().val().get(), key);
+            assert_eq!(
+                tree.first().map(|n| n.val().get()),
+                tree.iter().next().map(|v| v.get())
+            );
+        }
+        assert!(tree.pop_first().is_none());
+        assert!(tree.verify_tree());
+
+        for node in nodes.iter_mut().take(10) {
+            tree.insert(node).unwrap();
+        }
+        tree.clear();
+        assert!(tree.first().is_none() && nodes.iter().all(|n| !n.is_linked()));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{NoAugment, NodeDirection, RbAugment, RbError, RbNode, RbTrait, RbTree};
+
+// like std::collections::linked_list::CursorMut, a null current node is the
+// ghost position sitting between the last and the first node
+pub struct CursorMut<'a, Key, T, A = NoAugment> {
+    current: *mut RbNode<Key, T>,
+    tree: &'a mut RbTree<Key, T, A>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> CursorMut<'a, Key, T, A> {
+    pub(crate) fn new(tree: &'a mut RbTree<Key, T, A>, current: *mut RbNode<Key, T>) -> Self {
+        CursorMut { current, tree }
+    }
+
+    fn neighbour(&self, di: NodeDirection) -> *mut RbNode<Key, T> {
+        if self.current.is_null() {
+            RbNode::extreme(self.tree.root, di.opposite())
+        } else {
+            RbNode::neighbour(self.current, di)
+        }
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.neighbour(NodeDirection::RightChild);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.neighbour(NodeDirection::LeftChild);
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.current.as_mut().map(|n| &mut n.val) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.neighbour(NodeDirection::RightChild)
+                .as_mut()
+                .map(|n| &mut n.val)
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.neighbour(NodeDirection::LeftChild)
+                .as_mut()
+                .map(|n| &mut n.val)
+        }
+    }
+
+    /// Unlinks the current node and moves the cursor to its successor.
+    pub fn remove_current(&mut self) -> Option<&'a mut RbNode<Key, T>> {
+        let node = self.current;
+        if node.is_null() {
+            return None;
+        }
+
+        self.current = RbNode::neighbour(node, NodeDirection::RightChild);
+        unsafe {
+            self.tree.unlink(&mut *node);
+            Some(&mut *node)
+        }
+    }
+
+    /// Links `node` right after the current one, or at the front when the
+    /// cursor is at the ghost position. The caller keeps the ordering.
+    pub fn insert_after(&mut self, node: &mut RbNode<Key, T>) -> Result<(), RbError> {
+        self.insert_at(node, NodeDirection::RightChild)
+    }
+
+    /// Links `node` right before the current one, or at the back when the
+    /// cursor is at the ghost position. The caller keeps the ordering.
+    pub fn insert_before(&mut self, node: &mut RbNode<Key, T>) -> Result<(), RbError> {
+        self.insert_at(node, NodeDirection::LeftChild)
+    }
+
+    fn insert_at(&mut self, node: &mut RbNode<Key, T>, di: NodeDirection) -> Result<(), RbError> {
+        if node.is_linked() {
+            return Err(RbError::AlreadyLinked);
+        }
+
+        let next = self.neighbour(di);
+        let (parent, pd) = if self.current.is_null() {
+            (next, di.opposite())
+        } else {
+            unsafe {
+                let child = (*self.current).get_child(di);
+                if child.is_null() {
+                    (self.current, di)
+                } else {
+                    (next, di.opposite())
+                }
+            }
+        };
+
+        unsafe {
+            let (lo, hi) = if di == NodeDirection::RightChild {
+                (self.current, next)
+            } else {
+                (next, self.current)
+            };
+            debug_assert!(
+                (lo.is_null() || (*lo).val <= node.val) && (hi.is_null() || node.val <= (*hi).val),
+                "cursor insertion breaks the tree ordering"
+            );
+        }
+
+        self.tree.link_node(parent, pd, node);
+
+        #[cfg(test)]
+        assert!(self.tree.verify_tree());
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{NoAugment, NodeDirection, RbAugment, RbError, RbNode, RbTrait, RbTree};
+
+pub enum Entry<'a, Key, T, A = NoAugment> {
+    Occupied(OccupiedEntry<'a, Key, T, A>),
+    Vacant(VacantEntry<'a, Key, T, A>),
+}
+
+pub struct OccupiedEntry<'a, Key, T, A = NoAugment> {
+    node: *mut RbNode<Key, T>,
+    tree: &'a mut RbTree<Key, T, A>,
+}
+
+// parent and di are the slot the descent in RbTree::entry ended on, the
+// node is linked right there without searching again
+pub struct VacantEntry<'a, Key, T, A = NoAugment> {
+    key: Key,
+    parent: *mut RbNode<Key, T>,
+    di: NodeDirection,
+    tree: &'a mut RbTree<Key, T, A>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> Entry<'a, Key, T, A> {
+    /// Links `node` under the entry key if it is vacant.
+    pub fn or_insert(self, node: &'a mut RbNode<Key, T>) -> Result<&'a mut T, RbError> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(node),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut())
+        }
+        self
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> OccupiedEntry<'a, Key, T, A> {
+    pub(crate) fn new(tree: &'a mut RbTree<Key, T, A>, node: *mut RbNode<Key, T>) -> Self {
+        OccupiedEntry { node, tree }
+    }
+
+    pub fn get(&self) -> &T {
+        unsafe { &(*self.node).val }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.node).val }
+    }
+
+    pub fn into_mut(self) -> &'a mut T {
+        unsafe { &mut (*self.node).val }
+    }
+
+    /// Unlinks the node and hands it back.
+    pub fn remove(self) -> &'a mut RbNode<Key, T> {
+        unsafe {
+            self.tree.unlink(&mut *self.node);
+            &mut *self.node
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> VacantEntry<'a, Key, T, A> {
+    pub(crate) fn new(
+        tree: &'a mut RbTree<Key, T, A>,
+        key: Key,
+        parent: *mut RbNode<Key, T>,
+        di: NodeDirection,
+    ) -> Self {
+        VacantEntry {
+            key,
+            parent,
+            di,
+            tree,
+        }
+    }
+
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn into_key(self) -> Key {
+        self.key
+    }
+
+    /// Sets the key of `node` and links it where the lookup ended. A node
+    /// already in a tree is refused.
+    pub fn insert(self, node: &'a mut RbNode<Key, T>) -> Result<&'a mut T, RbError> {
+        if node.is_linked() {
+            return Err(RbError::AlreadyLinked);
+        }
+
+        Ok(self.insert_with(|key| {
+            node.set(key);
+            node
+        }))
+    }
+
+    // links the node make builds around the entry key, which is moved into
+    // it rather than set; the node has to be in no tree
+    pub(crate) fn insert_with<F>(self, make: F) -> &'a mut T
+    where
+        F: FnOnce(Key) -> &'a mut RbNode<Key, T>,
+    {
+        let node = make(self.key);
+        self.tree.link_node(self.parent, self.di, node);
+
+        #[cfg(test)]
+        assert!(self.tree.verify_tree());
+        &mut node.val
+    }
+}
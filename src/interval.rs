@@ -0,0 +1,341 @@
+use crate::{NodeDirection, RbAugment, RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::ptr::null_mut;
+
+// the half open interval [start, end) of a node, max_end is the largest end
+// in its subtree and value is only None until the tree fills it in
+pub(crate) struct IntervalEntry<K, V> {
+    start: K,
+    end: K,
+    max_end: K,
+    value: Option<V>,
+}
+
+impl<K: Copy, V> RbTrait<K> for IntervalEntry<K, V> {
+    fn new(key: K) -> Self {
+        IntervalEntry {
+            start: key,
+            end: key,
+            max_end: key,
+            value: None,
+        }
+    }
+
+    fn get(&self) -> K {
+        self.start
+    }
+
+    fn set(&mut self, key: K) {
+        self.start = key
+    }
+}
+
+impl<K: PartialEq, V> PartialEq for IntervalEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for IntervalEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.start.partial_cmp(&other.start)
+    }
+}
+
+type IntervalNode<K, V> = RbNode<K, IntervalEntry<K, V>>;
+
+pub(crate) struct MaxEnd;
+
+fn max_end<K: Ord + Copy, V>(node: &IntervalNode<K, V>) -> K {
+    let mut max_end = node.val.end;
+    if let Some(left) = node.left() {
+        max_end = max_end.max(left.val.max_end);
+    }
+    if let Some(right) = node.right() {
+        max_end = max_end.max(right.val.max_end);
+    }
+    max_end
+}
+
+impl<K: Ord + Copy, V> RbAugment<K, IntervalEntry<K, V>> for MaxEnd {
+    fn compute(node: &mut IntervalNode<K, V>) -> bool {
+        let max_end = max_end(node);
+        let changed = node.val.max_end != max_end;
+        node.val.max_end = max_end;
+        changed
+    }
+
+    fn verify(node: &IntervalNode<K, V>) -> bool {
+        node.val.max_end == max_end(node)
+    }
+}
+
+/// Half open intervals `[start, end)` with values, answering overlap
+/// queries in O(log n) per reported interval.
+pub struct IntervalTree<K, V> {
+    tree: RbTree<K, IntervalEntry<K, V>, MaxEnd>,
+    len: usize,
+}
+
+unsafe impl<K: Send, V: Send> Send for IntervalTree<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for IntervalTree<K, V> {}
+
+impl<K: Ord + Copy, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        IntervalTree {
+            tree: RbTree::new_augmented(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds `range`, equal intervals are kept side by side.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        let node = Box::leak(Box::new(IntervalNode::new(range.start)));
+        node.val.end = range.end;
+        node.val.value = Some(value);
+        self.tree.insert_node(node);
+        self.len += 1;
+    }
+
+    /// Removes one interval equal to `range`, returning its value.
+    pub fn remove(&mut self, range: &Range<K>) -> Option<V> {
+        let mut node = self.tree.first_above(&range.start, true);
+        unsafe {
+            while !node.is_null() && (*node).val.start == range.start {
+                if (*node).val.end == range.end {
+                    self.tree.unlink(&mut *node);
+                    self.len -= 1;
+                    return Box::from_raw(node).val.value;
+                }
+                node = RbNode::neighbour(node, NodeDirection::RightChild);
+            }
+        }
+        None
+    }
+
+    /// Every interval sharing at least one point with `range`, by start.
+    pub fn overlapping(&self, range: Range<K>) -> Overlapping<'_, K, V> {
+        self.search(Query {
+            lo: range.start,
+            hi: range.end,
+            hi_inclusive: false,
+        })
+    }
+
+    /// Every interval containing `point`, by start.
+    pub fn stab(&self, point: K) -> Overlapping<'_, K, V> {
+        self.search(Query {
+            lo: point,
+            hi: point,
+            hi_inclusive: true,
+        })
+    }
+
+    fn search(&self, query: Query<K>) -> Overlapping<'_, K, V> {
+        let root = self.tree.root;
+        let node = if root.is_null() || !query.reaches(unsafe { &(*root).val.max_end }) {
+            null_mut()
+        } else {
+            subtree_search(root, &query)
+        };
+
+        Overlapping {
+            node,
+            query,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Ord + Copy, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for IntervalTree<K, V> {
+    fn drop(&mut self) {
+        self.tree
+            .drain_postorder(|node| drop(unsafe { Box::from_raw(node) }));
+    }
+}
+
+struct Query<K> {
+    lo: K,
+    hi: K,
+    hi_inclusive: bool,
+}
+
+impl<K: Ord> Query<K> {
+    // an interval ending at end still reaches into the query
+    fn reaches(&self, end: &K) -> bool {
+        *end > self.lo
+    }
+
+    // an interval starting at start begins before the query is over
+    fn starts_before(&self, start: &K) -> bool {
+        *start < self.hi || (self.hi_inclusive && *start == self.hi)
+    }
+
+    fn overlaps<V>(&self, val: &IntervalEntry<K, V>) -> bool {
+        self.reaches(&val.end) && self.starts_before(&val.start)
+    }
+}
+
+// leftmost match below node, whose subtree is known to reach the query; the
+// walks follow Linux interval_tree_generic.h
+fn subtree_search<K: Ord, V>(
+    mut node: *mut IntervalNode<K, V>,
+    query: &Query<K>,
+) -> *mut IntervalNode<K, V> {
+    unsafe {
+        loop {
+            let left = (*node).childs[0];
+            if !left.is_null() && query.reaches(&(*left).val.max_end) {
+                node = left;
+                continue;
+            }
+
+            if !query.starts_before(&(*node).val.start) {
+                return null_mut();
+            }
+            if query.reaches(&(*node).val.end) {
+                return node;
+            }
+
+            let right = (*node).childs[1];
+            if right.is_null() || !query.reaches(&(*right).val.max_end) {
+                return null_mut();
+            }
+            node = right;
+        }
+    }
+}
+
+// the next match in order after node
+fn next_match<K: Ord, V>(
+    mut node: *mut IntervalNode<K, V>,
+    query: &Query<K>,
+) -> *mut IntervalNode<K, V> {
+    unsafe {
+        let mut right = (*node).childs[1];
+        loop {
+            if !right.is_null() && query.reaches(&(*right).val.max_end) {
+                return subtree_search(right, query);
+            }
+
+            // climb until coming up from a left child
+            loop {
+                let parent = (*node).parent;
+                if parent.is_null() {
+                    return null_mut();
+                }
+                let prev = node;
+                node = parent;
+                right = (*node).childs[1];
+                if prev != right {
+                    break;
+                }
+            }
+
+            if !query.starts_before(&(*node).val.start) {
+                return null_mut();
+            }
+            if query.overlaps(&(*node).val) {
+                return node;
+            }
+        }
+    }
+}
+
+pub struct Overlapping<'a, K, V> {
+    node: *mut IntervalNode<K, V>,
+    query: Query<K>,
+    marker: PhantomData<&'a IntervalNode<K, V>>,
+}
+
+impl<'a, K: Ord + Copy, V> Iterator for Overlapping<'a, K, V> {
+    type Item = (Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<(Range<K>, &'a V)> {
+        if self.node.is_null() {
+            return None;
+        }
+
+        let node = unsafe { &*self.node };
+        self.node = next_match(self.node, &self.query);
+        Some((
+            node.val.start..node.val.end,
+            node.val.value.as_ref().unwrap(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn against_brute_force() {
+        let mut array = [0u32; 600];
+        let mut tree = IntervalTree::<u32, usize>::new();
+        let mut model: Vec<(Range<u32>, usize)> = Vec::new();
+
+        let _ = utils::read_blocks_from_file::<u32>("/dev/urandom", &mut array, 600);
+        for (i, pair) in array[..400].chunks(2).enumerate() {
+            let start = pair[0] % 1000;
+            let range = start..start + pair[1] % 80;
+            tree.insert(range.clone(), i);
+            model.push((range, i));
+        }
+
+        for i in (0..model.len()).step_by(3).rev() {
+            let range = model[i].0.clone();
+            let value = tree.remove(&range).unwrap();
+            let pos = model.iter().position(|(r, v)| *r == range && *v == value);
+            model.remove(pos.unwrap());
+        }
+        assert_eq!(tree.len(), model.len());
+        assert!(tree.remove(&(2000..2001)).is_none());
+
+        for pair in array[400..].chunks(2) {
+            let start = pair[0] % 1100;
+            let query = start..start + pair[1] % 50;
+
+            let mut got: Vec<(Range<u32>, usize)> = tree
+                .overlapping(query.clone())
+                .map(|(r, v)| (r, *v))
+                .collect();
+            assert!(got.windows(2).all(|w| w[0].0.start <= w[1].0.start));
+            let mut want: Vec<(Range<u32>, usize)> = model
+                .iter()
+                .filter(|(r, _)| r.start < query.end && query.start < r.end)
+                .cloned()
+                .collect();
+            got.sort_by_key(|(_, v)| *v);
+            want.sort_by_key(|(_, v)| *v);
+            assert_eq!(got, want);
+
+            let mut got: Vec<usize> = tree.stab(start).map(|(_, v)| *v).collect();
+            let mut want: Vec<usize> = model
+                .iter()
+                .filter(|(r, _)| r.contains(&start))
+                .map(|(_, v)| *v)
+                .collect();
+            got.sort();
+            want.sort();
+            assert_eq!(got, want);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{NodeDirection, RbAugment, RbNode, RbTrait, RbTree};
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+
+pub struct Iter<'a, Key, T> {
+    head: *mut RbNode<Key, T>,
+    tail: *mut RbNode<Key, T>,
+    marker: PhantomData<&'a RbNode<Key, T>>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iter<'a, Key, T> {
+    pub(crate) fn new(head: *mut RbNode<Key, T>, tail: *mut RbNode<Key, T>) -> Self {
+        Iter {
+            head,
+            tail,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for Iter<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = step(&mut self.head, &mut self.tail, NodeDirection::RightChild);
+        unsafe { node.as_ref().map(|n| &n.val) }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> DoubleEndedIterator for Iter<'a, Key, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        let node = step(&mut self.tail, &mut self.head, NodeDirection::LeftChild);
+        unsafe { node.as_ref().map(|n| &n.val) }
+    }
+}
+
+pub struct IterMut<'a, Key, T> {
+    head: *mut RbNode<Key, T>,
+    tail: *mut RbNode<Key, T>,
+    marker: PhantomData<&'a mut RbNode<Key, T>>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> IterMut<'a, Key, T> {
+    pub(crate) fn new(head: *mut RbNode<Key, T>, tail: *mut RbNode<Key, T>) -> Self {
+        IterMut {
+            head,
+            tail,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for IterMut<'a, Key, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let node = step(&mut self.head, &mut self.tail, NodeDirection::RightChild);
+        unsafe { node.as_mut().map(|n| &mut n.val) }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> DoubleEndedIterator for IterMut<'a, Key, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        let node = step(&mut self.tail, &mut self.head, NodeDirection::LeftChild);
+        unsafe { node.as_mut().map(|n| &mut n.val) }
+    }
+}
+
+// yields the node at `from` and advances it towards di, both ends are
+// cleared once they meet so no node is returned twice
+fn step<Key, T: RbTrait<Key> + PartialOrd>(
+    from: &mut *mut RbNode<Key, T>,
+    to: &mut *mut RbNode<Key, T>,
+    di: NodeDirection,
+) -> *mut RbNode<Key, T> {
+    let node = *from;
+    if node.is_null() {
+        return node;
+    }
+
+    if node == *to {
+        *from = null_mut();
+        *to = null_mut();
+    } else {
+        *from = RbNode::neighbour(node, di);
+    }
+    node
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> IntoIterator
+    for &'a RbTree<Key, T, A>
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, Key, T>;
+
+    fn into_iter(self) -> Iter<'a, Key, T> {
+        self.iter()
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> IntoIterator
+    for &'a mut RbTree<Key, T, A>
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, Key, T>;
+
+    fn into_iter(self) -> IterMut<'a, Key, T> {
+        self.iter_mut()
+    }
+}
+
+pub struct Range<'a, Key, T> {
+    inner: Iter<'a, Key, T>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Range<'a, Key, T> {
+    pub(crate) fn new(head: *mut RbNode<Key, T>, tail: *mut RbNode<Key, T>) -> Self {
+        Range {
+            inner: Iter::new(head, tail),
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for Range<'a, Key, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next()
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> DoubleEndedIterator for Range<'a, Key, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.inner.next_back()
+    }
+}
+
+pub struct RangeMut<'a, Key, T> {
+    inner: IterMut<'a, Key, T>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> RangeMut<'a, Key, T> {
+    pub(crate) fn new(head: *mut RbNode<Key, T>, tail: *mut RbNode<Key, T>) -> Self {
+        RangeMut {
+            inner: IterMut::new(head, tail),
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> Iterator for RangeMut<'a, Key, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.inner.next()
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> DoubleEndedIterator for RangeMut<'a, Key, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        self.inner.next_back()
+    }
+}
@@ -0,0 +1,297 @@
+use crate::rebalance;
+use crate::{NodeColor, NodeDirection, RbAugment, RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+use std::ptr::null_mut;
+
+// subtrees handled here are plain root pointers, they are turned back into
+// trees only when a rebalance needs one. Each one carries its black height,
+// so a join walks only the difference in height instead of both spines
+
+// a standalone subtree, its root black and without parent, and its black
+// height, which is 0 for the empty one
+pub(crate) type Subtree<Key, T> = (*mut RbNode<Key, T>, usize);
+
+pub(crate) type Parts<Key, T> = (Subtree<Key, T>, *mut RbNode<Key, T>, Subtree<Key, T>);
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    // black nodes on any way down from node, node included
+    fn black_height(mut node: *mut RbNode<Key, T>) -> usize {
+        let mut height = 0;
+        while !node.is_null() {
+            unsafe {
+                if (*node).is_black() {
+                    height += 1;
+                }
+                node = (*node).get_child(NodeDirection::LeftChild);
+            }
+        }
+        height
+    }
+
+    // the whole tree as a subtree, measured once
+    pub(crate) fn subtree(&self) -> Subtree<Key, T> {
+        (self.root, Self::black_height(self.root))
+    }
+
+    // turns the subtree below node into a standalone one with a black root
+    pub(crate) fn detach(node: *mut RbNode<Key, T>) -> *mut RbNode<Key, T> {
+        if !node.is_null() {
+            unsafe {
+                (*node).parent = null_mut();
+                (*node).color = NodeColor::Black;
+            }
+        }
+        node
+    }
+
+    // the children of a non-empty subtree as standalone ones, a red child
+    // gains a black level as it turns black
+    pub(crate) fn children((node, height): Subtree<Key, T>) -> (Subtree<Key, T>, Subtree<Key, T>) {
+        #[cfg(test)]
+        assert_eq!(Self::black_height(node), height);
+
+        let child = |di| unsafe {
+            let child = (*node).get_child(di);
+            let red = !child.is_null() && (*child).is_red();
+            (Self::detach(child), if red { height } else { height - 1 })
+        };
+        (
+            child(NodeDirection::LeftChild),
+            child(NodeDirection::RightChild),
+        )
+    }
+
+    // links left, pivot and right, all of left sorting before pivot and all
+    // of right after it, walking only the difference in black height
+    pub(crate) fn join_raw(
+        (left, lh): Subtree<Key, T>,
+        pivot: *mut RbNode<Key, T>,
+        (right, rh): Subtree<Key, T>,
+    ) -> Subtree<Key, T> {
+        unsafe {
+            if lh == rh {
+                (*pivot).parent = null_mut();
+                (*pivot).color = NodeColor::Black;
+                (*pivot).set_child_without_color(left, NodeDirection::LeftChild);
+                (*pivot).set_child_without_color(right, NodeDirection::RightChild);
+                A::compute(&mut *pivot);
+                return (pivot, lh + 1);
+            }
+
+            let (tall, short, di, tall_height, short_height) = if lh > rh {
+                (left, right, NodeDirection::RightChild, lh, rh)
+            } else {
+                (right, left, NodeDirection::LeftChild, rh, lh)
+            };
+
+            // down the inner spine of the taller tree to a black node as
+            // high as the shorter tree
+            let mut height = tall_height;
+            let mut parent = null_mut::<RbNode<Key, T>>();
+            let mut node = tall;
+            while !((node.is_null() || (*node).is_black()) && height == short_height) {
+                if (*node).is_black() {
+                    height -= 1;
+                }
+                parent = node;
+                node = (*node).get_child(di);
+            }
+
+            (*pivot).color = NodeColor::Red;
+            (*pivot).set_child_without_color(node, di.opposite());
+            (*pivot).set_child_without_color(short, di);
+            (*parent).insert_child(di, pivot);
+            A::compute(&mut *pivot);
+            A::propagate(&mut *parent, null_mut());
+
+            let mut tree = Self::new_augmented();
+            tree.root = tall;
+            let grew = rebalance::insert_fixup(&mut tree, pivot);
+            (tree.root, tall_height + usize::from(grew))
+        }
+    }
+
+    // takes the rightmost node out of a non-empty subtree, rejoining what is
+    // left on the way back up
+    fn split_last(tree: Subtree<Key, T>) -> (Subtree<Key, T>, *mut RbNode<Key, T>) {
+        let (left, right) = Self::children(tree);
+        if right.0.is_null() {
+            return (left, tree.0);
+        }
+
+        let (rest, last) = Self::split_last(right);
+        (Self::join_raw(left, tree.0, rest), last)
+    }
+
+    // like join_raw, taking the rightmost node of left as the pivot
+    pub(crate) fn join2_raw(left: Subtree<Key, T>, right: Subtree<Key, T>) -> Subtree<Key, T> {
+        if left.0.is_null() {
+            return right;
+        }
+        if right.0.is_null() {
+            return left;
+        }
+
+        let (rest, pivot) = Self::split_last(left);
+        Self::join_raw(rest, pivot, right)
+    }
+
+    // splits the subtree by cmp into the parts sorting before and after,
+    // plus the first node met comparing equal which comes out unlinked
+    pub(crate) fn split_raw<F: FnMut(&T) -> Ordering>(
+        tree: Subtree<Key, T>,
+        cmp: &mut F,
+    ) -> Parts<Key, T> {
+        let node = tree.0;
+        if node.is_null() {
+            return ((null_mut(), 0), null_mut(), (null_mut(), 0));
+        }
+
+        let (left, right) = Self::children(tree);
+        unsafe {
+            match cmp(&(*node).val) {
+                Ordering::Equal => {
+                    (*node).clear_links();
+                    (left, node, right)
+                }
+                Ordering::Greater => {
+                    let (l, eq, r) = Self::split_raw(left, cmp);
+                    (l, eq, Self::join_raw(r, node, right))
+                }
+                Ordering::Less => {
+                    let (l, eq, r) = Self::split_raw(right, cmp);
+                    (Self::join_raw(left, node, l), eq, r)
+                }
+            }
+        }
+    }
+
+    /// Moves every node of `other` into `self`. Either tree may hold the
+    /// smaller values, but their ranges must not overlap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two ranges overlap.
+    pub fn append(&mut self, other: &mut Self) -> &mut Self {
+        let (Some(first), Some(last)) = (self.first(), self.last()) else {
+            std::mem::swap(&mut self.root, &mut other.root);
+            return self;
+        };
+        let (Some(other_first), Some(other_last)) = (other.first(), other.last()) else {
+            return self;
+        };
+
+        let (low, high) = if last.val <= other_first.val {
+            (self.subtree(), other.subtree())
+        } else if other_last.val <= first.val {
+            (other.subtree(), self.subtree())
+        } else {
+            panic!("appended trees overlap");
+        };
+        self.root = Self::join2_raw(low, high).0;
+        other.root = null_mut();
+
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        self
+    }
+}
+
+impl<Key: PartialOrd, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    /// Moves every node with a key `>= key` into the returned tree.
+    pub fn split_off(&mut self, key: &Key) -> Self {
+        let (left, _, right) = Self::split_raw(self.subtree(), &mut |val: &T| {
+            if val.cmp_key(key) == Some(Ordering::Less) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        });
+
+        let mut other = Self::new_augmented();
+        self.root = left.0;
+        other.root = right.0;
+
+        #[cfg(test)]
+        assert!(self.verify_tree() && other.verify_tree());
+        other
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{SumAugment, Summed, Test};
+    use crate::utils;
+    use crate::{RbNode, RbTrait, RbTree};
+
+    #[test]
+    fn split_append() {
+        let mut array = [0i32; 300];
+        let mut nodes: [RbNode<i32, Test>; 300] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 300);
+        let mut model: Vec<i32> = array.iter().map(|x| x.rem_euclid(200)).collect();
+        for (node, key) in nodes.iter_mut().zip(model.iter()) {
+            node.set(*key);
+            tree.insert(node).unwrap();
+        }
+        model.sort();
+
+        let keys = |tree: &RbTree<i32, Test>| tree.iter().map(|v| v.get()).collect::<Vec<_>>();
+        for at in [-1, 0, 37, 100, 199, 250] {
+            let mut upper = tree.split_off(&at);
+            assert_eq!(
+                keys(&tree),
+                model
+                    .iter()
+                    .copied()
+                    .filter(|&x| x < at)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                keys(&upper),
+                model
+                    .iter()
+                    .copied()
+                    .filter(|&x| x >= at)
+                    .collect::<Vec<_>>()
+            );
+
+            // join back in either order
+            if at % 2 == 0 {
+                tree.append(&mut upper);
+            } else {
+                upper.append(&mut tree);
+                std::mem::swap(&mut tree, &mut upper);
+            }
+            assert!(upper.first().is_none());
+            assert_eq!(keys(&tree), model);
+        }
+
+        // three parts, the middle one taking the others from both sides
+        let mut upper = tree.split_off(&150);
+        let mut middle = tree.split_off(&100);
+        middle.append(&mut upper).append(&mut tree);
+        assert!(tree.first().is_none());
+        assert_eq!(keys(&middle), model);
+    }
+
+    #[test]
+    fn split_append_augmented() {
+        let mut nodes: Vec<RbNode<i32, Summed>> = (0..100).map(RbNode::new).collect();
+        let mut tree = RbTree::<i32, Summed, SumAugment>::new_augmented();
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        let sum = |tree: &RbTree<i32, Summed, SumAugment>| {
+            unsafe { tree.root.as_ref() }.map_or(0, |n| n.val().sum)
+        };
+        let mut upper = tree.split_off(&30);
+        assert_eq!((sum(&tree), sum(&upper)), ((0..30).sum(), (30..100).sum()));
+        upper.append(&mut tree);
+        assert_eq!(sum(&upper), (0..100).sum());
+    }
+}
@@ -1,7 +1,42 @@
+#[cfg(test)]
 mod utils;
 
+pub mod adapter;
+mod algebra;
+pub mod arena;
+mod cached;
+mod cursor;
+mod entry;
+pub mod interval;
+mod iter;
+mod join;
+pub mod map;
+mod order;
+mod pinned;
+mod queue;
+mod rebalance;
+pub mod set;
+mod visit;
+
+pub use adapter::{RbAdapter, RbAdapterTree, RbLink};
+pub use algebra::{Difference, Intersection, SymmetricDifference, Union};
+pub use arena::ArenaRbTree;
+pub use cached::RbTreeCached;
+pub use cursor::CursorMut;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use interval::IntervalTree;
+pub use iter::{Iter, IterMut, Range, RangeMut};
+pub use map::RbMap;
+pub use order::{RbCounted, SubtreeSize};
+pub use pinned::RbTreePinned;
+pub use queue::RbPriorityQueue;
+pub use set::RbSet;
+
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Write;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::ptr::{null, null_mut};
 
 #[derive(PartialEq, Copy, Clone)]
@@ -19,6 +54,16 @@ impl From<NodeDirection> for usize {
     }
 }
 
+impl NodeDirection {
+    #[inline]
+    fn opposite(self) -> NodeDirection {
+        match self {
+            Self::LeftChild => Self::RightChild,
+            Self::RightChild => Self::LeftChild,
+        }
+    }
+}
+
 impl From<bool> for NodeDirection {
     fn from(branch: bool) -> NodeDirection {
         match branch {
@@ -47,16 +92,150 @@ pub trait RbTrait<Key> {
     fn new(key: Key) -> Self;
     fn get(&self) -> Key;
     fn set(&mut self, key: Key);
+
+    /// Compares the key of `self` with `key`, through `get` by default;
+    /// values holding their key can compare it in place instead.
+    fn cmp_key(&self, key: &Key) -> Option<Ordering>
+    where
+        Key: PartialOrd,
+    {
+        self.get().partial_cmp(key)
+    }
+}
+
+/// Keeps per-subtree summaries stored in `T` up to date while the tree
+/// changes shape, like Linux `rb_augment_callbacks`.
+pub trait RbAugment<Key, T> {
+    /// Recomputes the summary of `node` from its value and its children,
+    /// returning whether the summary changed.
+    fn compute(node: &mut RbNode<Key, T>) -> bool;
+
+    /// Whether the summary stored in `node` matches its value and children,
+    /// checked by `verify_tree` without changing anything.
+    fn verify(node: &RbNode<Key, T>) -> bool;
+
+    /// Refreshes summaries from `node` up to, not including, `stop`. Stops
+    /// early once a summary comes out unchanged.
+    fn propagate(node: &mut RbNode<Key, T>, stop: *const RbNode<Key, T>) {
+        let mut node: *mut RbNode<Key, T> = node;
+        while !node.is_null() && !std::ptr::eq(node, stop) {
+            unsafe {
+                if !Self::compute(&mut *node) {
+                    break;
+                }
+                node = (*node).parent;
+            }
+        }
+    }
+
+    /// `new` took the place of `old` keeping its children.
+    fn copy(old: &RbNode<Key, T>, new: &mut RbNode<Key, T>) {
+        let _ = old;
+        Self::compute(new);
+    }
+
+    /// `new` was rotated into the place of `old`, which is now its child.
+    fn rotate(old: &mut RbNode<Key, T>, new: &mut RbNode<Key, T>) {
+        Self::compute(old);
+        Self::compute(new);
+    }
+}
+
+/// The augmentation of plain trees, keeping no summary at all.
+pub struct NoAugment;
+
+impl<Key, T> RbAugment<Key, T> for NoAugment {
+    fn compute(_: &mut RbNode<Key, T>) -> bool {
+        false
+    }
+
+    fn verify(_: &RbNode<Key, T>) -> bool {
+        true
+    }
+
+    fn propagate(_: &mut RbNode<Key, T>, _: *const RbNode<Key, T>) {}
+
+    fn copy(_: &RbNode<Key, T>, _: &mut RbNode<Key, T>) {}
+
+    fn rotate(_: &mut RbNode<Key, T>, _: &mut RbNode<Key, T>) {}
+}
+
+/// Why a node could not be linked into or unlinked from a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbError {
+    /// The node is not in any tree.
+    NotLinked,
+    /// The node is in a tree already.
+    AlreadyLinked,
+    /// The node is in another tree, only noticed in debug builds.
+    WrongTree,
+}
+
+impl fmt::Display for RbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RbError::NotLinked => write!(f, "node is not linked into a tree"),
+            RbError::AlreadyLinked => write!(f, "node is already linked into a tree"),
+            RbError::WrongTree => write!(f, "node is linked into another tree"),
+        }
+    }
+}
+
+impl std::error::Error for RbError {}
+
+/// Why [`RbTree::insert_unique`] did not link a node.
+pub enum UniqueError<'a, Key, T> {
+    /// The node is in a tree already.
+    AlreadyLinked,
+    /// An equal value is present, held by this node.
+    Exists(&'a mut RbNode<Key, T>),
+}
+
+impl<Key, T> fmt::Debug for UniqueError<'_, Key, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UniqueError::AlreadyLinked => write!(f, "AlreadyLinked"),
+            UniqueError::Exists(_) => write!(f, "Exists"),
+        }
+    }
 }
 
-pub struct RbNode<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> {
+pub struct RbNode<Key, T> {
     color: NodeColor,
     val: T,
     parent: *mut RbNode<Key, T>,
     childs: [*mut RbNode<Key, T>; 2],
 }
 
-impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
+// a node out of any tree is red with no parent, while a linked one either
+// has a parent or is the always black root
+impl<Key, T> RbNode<Key, T> {
+    /// Whether the node is in a tree. Trees unlink their nodes on
+    /// [`RbTree::clear`] but not when dropped, a node of a tree dropped
+    /// without being cleared stays linked until [`RbNode::reset`].
+    pub fn is_linked(&self) -> bool {
+        !self.parent.is_null() || self.color == NodeColor::Black
+    }
+
+    /// Marks the node unlinked, so a node left behind by a dropped tree can
+    /// go into another one.
+    ///
+    /// # Safety
+    ///
+    /// No live tree may still hold the node.
+    pub unsafe fn reset(&mut self) {
+        self.clear_links();
+    }
+
+    // marks the node unlinked, like Linux RB_CLEAR_NODE
+    fn clear_links(&mut self) {
+        self.color = NodeColor::Red;
+        self.parent = null_mut();
+        self.childs = [null_mut(), null_mut()];
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd> RbNode<Key, T> {
     pub fn new(key: Key) -> Self {
         RbNode {
             color: NodeColor::Red,
@@ -70,6 +249,34 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
         self.val.set(key)
     }
 
+    pub fn val(&self) -> &T {
+        &self.val
+    }
+
+    pub fn val_mut(&mut self) -> &mut T {
+        &mut self.val
+    }
+
+    pub fn left(&self) -> Option<&RbNode<Key, T>> {
+        unsafe { self.get_child(NodeDirection::LeftChild).as_ref() }
+    }
+
+    pub fn right(&self) -> Option<&RbNode<Key, T>> {
+        unsafe { self.get_child(NodeDirection::RightChild).as_ref() }
+    }
+
+    /// In-order successor, found through the parent links.
+    pub fn next(&self) -> Option<&RbNode<Key, T>> {
+        let node = self as *const RbNode<Key, T> as *mut RbNode<Key, T>;
+        unsafe { Self::neighbour(node, NodeDirection::RightChild).as_ref() }
+    }
+
+    /// In-order predecessor, found through the parent links.
+    pub fn prev(&self) -> Option<&RbNode<Key, T>> {
+        let node = self as *const RbNode<Key, T> as *mut RbNode<Key, T>;
+        unsafe { Self::neighbour(node, NodeDirection::LeftChild).as_ref() }
+    }
+
     #[inline]
     fn get_child(&self, child: NodeDirection) -> *mut RbNode<Key, T> {
         self.childs[usize::from(child)]
@@ -108,22 +315,13 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
         self.color == NodeColor::Red
     }
 
-    #[inline]
-    fn get_parent(&self) -> *mut RbNode<Key, T> {
-        self.parent
-    }
-
-    #[inline]
-    fn set_color(&mut self, color: NodeColor) {
-        self.color = color
-    }
-
     #[inline]
     fn get_direction(&mut self, parent: *mut RbNode<Key, T>) -> NodeDirection {
         unsafe {
-            if (*parent).childs[usize::from(NodeDirection::LeftChild)]
-                == self as *mut RbNode<Key, T>
-            {
+            if std::ptr::eq(
+                (*parent).childs[usize::from(NodeDirection::LeftChild)],
+                self,
+            ) {
                 NodeDirection::LeftChild
             } else {
                 NodeDirection::RightChild
@@ -131,49 +329,12 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
         }
     }
 
-    #[inline]
-    fn get_the_other_child_of_color(
-        &self,
-        di: &NodeDirection,
-        color: NodeColor,
-    ) -> *mut RbNode<Key, T> {
-        let sel = match di {
-            NodeDirection::LeftChild => 1,
-            NodeDirection::RightChild => 0,
-        };
-
-        let child = self.childs[sel];
-
-        return if child.is_null() {
-            null_mut::<RbNode<Key, T>>()
-        } else {
-            unsafe {
-                if (*child).color == color {
-                    child
-                } else {
-                    null_mut::<RbNode<Key, T>>()
-                }
-            }
-        };
-    }
-
     #[inline]
     fn insert_child(&mut self, di: NodeDirection, node: *mut RbNode<Key, T>) {
         self.childs[usize::from(di)] = node;
         unsafe { (*node).parent = self }
     }
 
-    #[inline]
-    fn set_child(&mut self, child: *mut RbNode<Key, T>, di: NodeDirection, color: NodeColor) {
-        self.childs[usize::from(di)] = child;
-        unsafe {
-            if !child.is_null() {
-                (*child).parent = self;
-                (*child).color = color
-            }
-        }
-    }
-
     #[inline]
     fn set_child_without_color(&mut self, child: *mut RbNode<Key, T>, di: NodeDirection) {
         self.childs[usize::from(di)] = child;
@@ -183,23 +344,7 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
     }
 
     #[inline]
-    fn rotate_with_parent(
-        &mut self,
-        parent: *mut RbNode<Key, T>,
-        di: NodeDirection,
-        color: NodeColor,
-    ) {
-        let other = if di == NodeDirection::LeftChild {
-            NodeDirection::RightChild
-        } else {
-            NodeDirection::LeftChild
-        };
-        unsafe { (*parent).set_child_without_color(self.childs[usize::from(other)], di) }
-        self.set_child(parent, other, color)
-    }
-
-    #[inline]
-    fn inherit_parent(&mut self, node: *mut RbNode<Key, T>, tree: &mut RbTree<Key, T>) {
+    fn inherit_parent<A>(&mut self, node: *mut RbNode<Key, T>, tree: &mut RbTree<Key, T, A>) {
         unsafe {
             let parent = (*node).parent;
             self.parent = parent;
@@ -220,20 +365,138 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbNode<Key, T> {
             unsafe { (*child).parent = self }
         }
     }
+
+    // the outermost node of the subtree below node towards di
+    fn extreme(mut node: *mut RbNode<Key, T>, di: NodeDirection) -> *mut RbNode<Key, T> {
+        if !node.is_null() {
+            unsafe {
+                while !(*node).get_child(di).is_null() {
+                    node = (*node).get_child(di);
+                }
+            }
+        }
+        node
+    }
+
+    // the in-order neighbour of node towards di, null if there is none
+    fn neighbour(mut node: *mut RbNode<Key, T>, di: NodeDirection) -> *mut RbNode<Key, T> {
+        unsafe {
+            let child = (*node).get_child(di);
+            if !child.is_null() {
+                return Self::extreme(child, di.opposite());
+            }
+
+            let mut parent = (*node).parent;
+            while !parent.is_null() && (*node).get_direction(parent) == di {
+                node = parent;
+                parent = (*node).parent;
+            }
+            parent
+        }
+    }
 }
 
 const INITIAL_BLACK_COUNTER: i32 = -1;
 
-pub struct RbTree<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> {
+pub struct RbTree<Key, T, A = NoAugment> {
     root: *mut RbNode<Key, T>,
+    marker: PhantomData<A>,
 }
 
-impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbTree<Key, T> {
+impl<Key, T: RbTrait<Key> + PartialOrd> RbTree<Key, T> {
     pub fn new() -> Self {
-        RbTree { root: null_mut() }
+        Self::new_augmented()
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    /// An empty tree keeping its summaries up to date through `A`.
+    pub fn new_augmented() -> Self {
+        RbTree {
+            root: null_mut(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Builds a balanced tree out of nodes already in order in O(n),
+    /// without any rotation.
+    pub fn from_sorted_nodes<'a, I>(nodes: I) -> Result<Self, RbError>
+    where
+        I: IntoIterator<Item = &'a mut RbNode<Key, T>>,
+        Key: 'a,
+        T: 'a,
+    {
+        let nodes: Vec<*mut RbNode<Key, T>> = nodes
+            .into_iter()
+            .map(|n| {
+                if n.is_linked() {
+                    Err(RbError::AlreadyLinked)
+                } else {
+                    Ok(n as *mut _)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        debug_assert!(
+            nodes
+                .windows(2)
+                .all(|w| unsafe { (*w[0]).val <= (*w[1]).val }),
+            "nodes are not sorted"
+        );
+
+        // every level above red_depth is full and black, the partial level
+        // left at the bottom is red
+        let red_depth = (nodes.len() + 1).ilog2() as usize;
+        let mut tree = Self::new_augmented();
+        tree.root = Self::build_balanced(&nodes, null_mut(), 0, red_depth);
+
+        #[cfg(test)]
+        assert!(tree.verify_tree());
+        Ok(tree)
+    }
+
+    fn build_balanced(
+        nodes: &[*mut RbNode<Key, T>],
+        parent: *mut RbNode<Key, T>,
+        depth: usize,
+        red_depth: usize,
+    ) -> *mut RbNode<Key, T> {
+        if nodes.is_empty() {
+            return null_mut();
+        }
+
+        let mid = nodes.len() / 2;
+        let node = nodes[mid];
+        unsafe {
+            (*node).parent = parent;
+            (*node).color = if depth == red_depth {
+                NodeColor::Red
+            } else {
+                NodeColor::Black
+            };
+            (*node).childs = [
+                Self::build_balanced(&nodes[..mid], node, depth + 1, red_depth),
+                Self::build_balanced(&nodes[mid + 1..], node, depth + 1, red_depth),
+            ];
+            A::compute(&mut *node);
+        }
+        node
+    }
+
+    /// Links `node`, keeping any number of equal values. An equal value is
+    /// placed after the ones already present, so duplicates iterate in
+    /// insertion order. A node already in a tree is refused.
+    pub fn insert(&mut self, node: &mut RbNode<Key, T>) -> Result<&mut Self, RbError> {
+        if node.is_linked() {
+            return Err(RbError::AlreadyLinked);
+        }
+
+        self.insert_node(node);
+        Ok(self)
     }
 
-    pub fn insert(&mut self, node: &mut RbNode<Key, T>) -> &mut Self {
+    // links node, known to be in no tree; the tree keeps node as given, so
+    // whatever node may reach besides itself stays reachable
+    fn insert_node(&mut self, node: *mut RbNode<Key, T>) {
         let mut parent: *mut RbNode<Key, T> = null_mut();
         let mut p = self.root;
         let mut branch: bool = false;
@@ -241,250 +504,218 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbTree<Key, T> {
         while !p.is_null() {
             parent = p;
             unsafe {
-                branch = (*p).val.lt(&(*node).val);
+                branch = (*p).val.le(&(*node).val);
                 p = (*p).get_child(NodeDirection::from(branch));
             }
         }
 
-        if parent.is_null() {
-            // root node
-            self.root = node;
-            node.color = NodeColor::Black
-        } else {
-            unsafe { (*parent).insert_child(NodeDirection::from(branch), node) }
-            self.insert_rebalance(node)
-        }
+        self.link_node(parent, NodeDirection::from(branch), node);
 
         #[cfg(test)]
         assert!(self.verify_tree());
-        self
     }
 
-    fn insert_rebalance(&mut self, mut node: *mut RbNode<Key, T>) {
-        let mut p: *mut RbNode<Key, T>;
-        let mut gp: *mut RbNode<Key, T>;
-        let mut nd: NodeDirection;
-        let mut pd: NodeDirection;
+    /// Links `node` only if no equal value is present, otherwise the node
+    /// already holding it is returned, like Linux `rb_find_add`. A node
+    /// already in a tree is refused.
+    pub fn insert_unique(
+        &mut self,
+        node: &mut RbNode<Key, T>,
+    ) -> Result<&mut Self, UniqueError<'_, Key, T>> {
+        if node.is_linked() {
+            return Err(UniqueError::AlreadyLinked);
+        }
 
-        loop {
-            p = unsafe { (*node).get_parent() };
-            if p.is_null() {
-                unsafe { (*node).set_color(NodeColor::Black) };
-                self.root = node;
-                break;
-            }
+        let mut parent: *mut RbNode<Key, T> = null_mut();
+        let mut p = self.root;
+        let mut branch: bool = false;
 
-            if unsafe { (*p).is_black() } {
-                break;
+        while !p.is_null() {
+            parent = p;
+            unsafe {
+                if node.val < (*p).val {
+                    branch = false;
+                } else if (*p).val < node.val {
+                    branch = true;
+                } else {
+                    return Err(UniqueError::Exists(&mut *p));
+                }
+                p = (*p).get_child(NodeDirection::from(branch));
             }
+        }
 
-            gp = unsafe { (*p).get_parent() };
-            nd = unsafe { (*node).get_direction(p) };
-            pd = unsafe { (*p).get_direction(gp) };
-            let uncle = unsafe { (*gp).get_the_other_child_of_color(&pd, NodeColor::Red) };
-            if !uncle.is_null() {
-                unsafe {
-                    (*p).set_color(NodeColor::Black);
-                    (*uncle).set_color(NodeColor::Black);
-                    node = gp;
-                    (*node).set_color(NodeColor::Red);
-                    continue;
-                }
-            } else {
-                if nd != pd {
-                    unsafe { (*node).rotate_with_parent(p, nd, NodeColor::Red) }
-                    nd = pd;
-                    p = node;
-                }
+        self.link_node(parent, NodeDirection::from(branch), node);
+
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        Ok(self)
+    }
 
+    /// Puts `new` in the exact place of `old` without rebalancing, like Linux
+    /// `rb_replace_node`. `new` has to sort the same as `old` does. `old`
+    /// has to be in this tree and `new` in none.
+    pub fn replace(
+        &mut self,
+        old: &mut RbNode<Key, T>,
+        new: &mut RbNode<Key, T>,
+    ) -> Result<&mut Self, RbError> {
+        self.check_member(old)?;
+        if new.is_linked() {
+            return Err(RbError::AlreadyLinked);
+        }
+        debug_assert!(
+            {
+                let prev = RbNode::neighbour(old, NodeDirection::LeftChild);
+                let next = RbNode::neighbour(old, NodeDirection::RightChild);
                 unsafe {
-                    (*p).inherit_parent(gp, self);
-                    (*p).rotate_with_parent(gp, nd, NodeColor::Red)
+                    (prev.is_null() || (*prev).val <= new.val)
+                        && (next.is_null() || new.val <= (*next).val)
                 }
-                break;
-            }
+            },
+            "replacement breaks the tree ordering"
+        );
+
+        new.inherit_parent(old, self);
+        new.hook_old_child(old.childs[0], NodeDirection::LeftChild);
+        new.hook_old_child(old.childs[1], NodeDirection::RightChild);
+        A::copy(old, new);
+        // new may differ from old in what the summaries are made of
+        if let Some(parent) = unsafe { new.parent.as_mut() } {
+            A::propagate(parent, null());
         }
-    }
+        old.clear_links();
 
-    pub fn delete(&mut self, node: &mut RbNode<Key, T>) -> &mut Self {
-        let left = node.childs[0];
-        let right = node.childs[1];
-        let mut di = NodeDirection::LeftChild;
+        #[cfg(test)]
+        assert!(self.verify_tree());
+        Ok(self)
+    }
 
-        let mut fix = null_mut::<RbNode<Key, T>>();
+    // hooks node below parent towards di, or as the root when parent is
+    // null; callers have made sure node is in no tree
+    fn link_node(
+        &mut self,
+        parent: *mut RbNode<Key, T>,
+        di: NodeDirection,
+        node: *mut RbNode<Key, T>,
+    ) {
+        debug_assert!(unsafe { !(*node).is_linked() }, "node is already linked");
+        rebalance::link(self, parent, di, node);
+    }
 
-        if left.is_null() {
-            if !right.is_null() {
-                unsafe { (*right).inherit_parent(node, self) }
-            } else {
-                let p = node.get_parent();
-                if p.is_null() {
-                    self.root = null_mut()
-                } else {
-                    di = node.get_direction(p);
-                    unsafe {
-                        (*p).childs[usize::from(di)] = null_mut();
-                    }
-                    if node.is_black() {
-                        fix = p;
-                    }
-                }
-            }
-        } else if right.is_null() {
-            unsafe { (*left).inherit_parent(node, self) }
-        } else {
-            let mut far_left: *mut RbNode<Key, T>;
-            let near_right: *mut RbNode<Key, T>;
-            let mut x = right;
-
-            loop {
-                far_left = x;
-                unsafe { x = (*x).childs[0] }
-                if x.is_null() {
-                    break;
-                }
-            }
+    /// Unlinks `node`, refusing one which is in no tree. Debug builds also
+    /// refuse a node of another tree.
+    pub fn delete(&mut self, node: &mut RbNode<Key, T>) -> Result<&mut Self, RbError> {
+        self.check_member(node)?;
+        self.unlink(node);
+        Ok(self)
+    }
 
-            near_right = unsafe { (*far_left).childs[1] };
-            let need_fix = unsafe { (*far_left).is_black() && near_right.is_null() };
-            if far_left != right {
-                unsafe {
-                    fix = (*far_left).parent;
-                    (*fix).set_child(near_right, NodeDirection::LeftChild, NodeColor::Black);
-                    (*far_left).hook_old_child(right, NodeDirection::RightChild);
-                }
-            } else {
-                fix = far_left;
-                di = NodeDirection::RightChild;
-                if !near_right.is_null() {
-                    unsafe { (*near_right).color = NodeColor::Black }
-                }
-            }
+    // whether node can be unlinked from this tree, the walk up to the root
+    // of its tree is left to debug builds
+    pub(crate) fn check_member(&self, node: &RbNode<Key, T>) -> Result<(), RbError> {
+        if !node.is_linked() {
+            return Err(RbError::NotLinked);
+        }
 
-            if !need_fix {
-                fix = null_mut();
-            };
+        #[cfg(debug_assertions)]
+        if !self.owns(node) {
+            return Err(RbError::WrongTree);
+        }
+        Ok(())
+    }
 
-            unsafe {
-                (*far_left).inherit_parent(node, self);
-                (*far_left).hook_old_child(left, NodeDirection::LeftChild);
+    fn owns(&self, node: &RbNode<Key, T>) -> bool {
+        let mut top: *const RbNode<Key, T> = node;
+        unsafe {
+            while !(*top).parent.is_null() {
+                top = (*top).parent;
             }
         }
+        std::ptr::eq(top, self.root)
+    }
 
-        if !fix.is_null() {
-            self.delete_reblance(fix, di)
-        }
+    // takes node, known to be in this tree, out of it
+    fn unlink(&mut self, node: &mut RbNode<Key, T>) {
+        rebalance::unlink(self, node);
+        node.clear_links();
 
         #[cfg(test)]
         assert!(self.verify_tree());
-        self
     }
 
-    fn delete_reblance(&mut self, mut parent: *mut RbNode<Key, T>, mut nd: NodeDirection) {
-        let mut node: *mut RbNode<Key, T>;
-        unsafe {
-            loop {
-                let sd = if nd == NodeDirection::LeftChild {
-                    NodeDirection::RightChild
-                } else {
-                    NodeDirection::LeftChild
-                };
-                let mut s = (*parent).childs[usize::from(sd)];
-                let color = if s.is_null() {
-                    NodeColor::Black
-                } else {
-                    (*s).color
-                };
+    pub fn first(&self) -> Option<&RbNode<Key, T>> {
+        unsafe { RbNode::extreme(self.root, NodeDirection::LeftChild).as_ref() }
+    }
 
-                if color == NodeColor::Red {
-                    (*s).inherit_parent(parent, self);
-                    (*s).rotate_with_parent(parent, sd, NodeColor::Red);
-                    s = (*parent).childs[usize::from(sd)];
-                }
+    pub fn last(&self) -> Option<&RbNode<Key, T>> {
+        unsafe { RbNode::extreme(self.root, NodeDirection::RightChild).as_ref() }
+    }
 
-                let left = (*s).childs[0];
-                let left_color = if left.is_null() {
-                    NodeColor::Black
-                } else {
-                    (*left).color
-                };
-                let right = (*s).childs[1];
-                let right_color = if right.is_null() {
-                    NodeColor::Black
-                } else {
-                    (*right).color
-                };
-
-                let sc = [left, right];
-                let scc = [left_color, right_color];
-
-                if left_color == NodeColor::Black && right_color == NodeColor::Black {
-                    (*s).color = NodeColor::Red;
-                    node = parent;
-                    if node != self.root && (*node).is_black() {
-                        nd = (*node).get_direction((*node).parent);
-                        parent = (*node).parent;
-                        continue;
-                    } else {
-                        break;
-                    }
-                } else {
-                    if scc[usize::from(sd)] == NodeColor::Black {
-                        (*sc[usize::from(nd)]).inherit_parent(s, self);
-                        (*sc[usize::from(nd)]).rotate_with_parent(s, nd, NodeColor::Red);
-                        s = (*s).parent;
-                    }
+    pub fn peek_first(&self) -> Option<&T> {
+        self.first().map(|n| &n.val)
+    }
 
-                    (*s).inherit_parent(parent, self);
-                    (*s).rotate_with_parent(parent, sd, NodeColor::Black);
-                    let sdc = (*s).childs[usize::from(sd)];
-                    if !sdc.is_null() {
-                        (*sdc).color = NodeColor::Black;
-                    }
-                    node = self.root;
-                    break;
-                }
-            }
-            (*node).color = NodeColor::Black;
-        }
+    pub fn peek_last(&self) -> Option<&T> {
+        self.last().map(|n| &n.val)
     }
 
-    pub fn traversal_preorder(&self, node: *mut RbNode<Key, T>, f: fn(*mut RbNode<Key, T>)) {
-        if !node.is_null() {
-            f(node);
-            self.traversal_preorder(unsafe { (*node).childs[0] }, f);
-            self.traversal_preorder(unsafe { (*node).childs[1] }, f);
-        }
+    /// Unlinks the leftmost node and hands it back.
+    pub fn pop_first(&mut self) -> Option<&mut RbNode<Key, T>> {
+        self.pop_extreme(NodeDirection::LeftChild)
     }
 
-    pub fn dump_tree(&self) {
-        self.traversal_preorder(self.root, |node| unsafe {
-            let parent = (*node).parent;
-            let left = (*node).childs[0];
-            let right = (*node).childs[1];
+    /// Unlinks the rightmost node and hands it back.
+    pub fn pop_last(&mut self) -> Option<&mut RbNode<Key, T>> {
+        self.pop_extreme(NodeDirection::RightChild)
+    }
 
-            let mut out = String::new();
-            let _ = write!(out, "{}({}): parent: ", (*node).color, (*node).val);
-            if parent.is_null() {
-                let _ = write!(out, "nil, left: ");
-            } else {
-                let _ = write!(out, "{}, left: ", (*parent).val);
-            }
+    fn pop_extreme(&mut self, di: NodeDirection) -> Option<&mut RbNode<Key, T>> {
+        let node = RbNode::extreme(self.root, di);
+        if node.is_null() {
+            return None;
+        }
 
-            if left.is_null() {
-                let _ = write!(out, "nil, right: ");
-            } else {
-                let _ = write!(out, "{}, right: ", (*left).val);
-            }
+        unsafe {
+            self.unlink(&mut *node);
+            Some(&mut *node)
+        }
+    }
 
-            if right.is_null() {
-                let _ = write!(out, "nil");
-            } else {
-                let _ = write!(out, "{}", (*right).val);
-            }
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, Key, T, A> {
+        let current = RbNode::extreme(self.root, NodeDirection::LeftChild);
+        CursorMut::new(self, current)
+    }
 
-            println!("{}", out);
-        })
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, Key, T, A> {
+        let current = RbNode::extreme(self.root, NodeDirection::RightChild);
+        CursorMut::new(self, current)
+    }
+
+    pub fn iter(&self) -> Iter<'_, Key, T> {
+        Iter::new(
+            RbNode::extreme(self.root, NodeDirection::LeftChild),
+            RbNode::extreme(self.root, NodeDirection::RightChild),
+        )
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, Key, T> {
+        IterMut::new(
+            RbNode::extreme(self.root, NodeDirection::LeftChild),
+            RbNode::extreme(self.root, NodeDirection::RightChild),
+        )
+    }
+
+    /// # Safety
+    ///
+    /// `node` must be null or a node linked into this tree.
+    pub unsafe fn traversal_preorder(&self, node: *mut RbNode<Key, T>, f: fn(*mut RbNode<Key, T>)) {
+        if !node.is_null() {
+            f(node);
+            unsafe {
+                self.traversal_preorder((*node).childs[0], f);
+                self.traversal_preorder((*node).childs[1], f);
+            }
+        }
     }
 
     fn verify_properties(
@@ -545,13 +776,229 @@ impl<Key: Default, T: RbTrait<Key> + PartialOrd + fmt::Display> RbTree<Key, T> {
         )
     }
 
+    // recomputes every summary bottom up, any of them changing means it
+    // was stale
+    fn verify_augment(node: *mut RbNode<Key, T>) -> bool {
+        if node.is_null() {
+            return true;
+        }
+
+        unsafe {
+            Self::verify_augment((*node).childs[0])
+                && Self::verify_augment((*node).childs[1])
+                && A::verify(&*node)
+        }
+    }
+
     pub fn verify_tree(&self) -> bool {
         let mut count = INITIAL_BLACK_COUNTER;
         if self.root.is_null() {
             return true;
         }
 
-        self.verify_properties(self.root, &mut count, 0) && self.verify_bst()
+        self.verify_properties(self.root, &mut count, 0)
+            && self.verify_bst()
+            && Self::verify_augment(self.root)
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd + fmt::Display, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    pub fn dump_tree(&self) {
+        let dump: fn(*mut RbNode<Key, T>) = |node| unsafe {
+            let parent = (*node).parent;
+            let left = (*node).childs[0];
+            let right = (*node).childs[1];
+
+            let mut out = String::new();
+            let _ = write!(out, "{}({}): parent: ", (*node).color, (*node).val);
+            if parent.is_null() {
+                let _ = write!(out, "nil, left: ");
+            } else {
+                let _ = write!(out, "{}, left: ", (*parent).val);
+            }
+
+            if left.is_null() {
+                let _ = write!(out, "nil, right: ");
+            } else {
+                let _ = write!(out, "{}, right: ", (*left).val);
+            }
+
+            if right.is_null() {
+                let _ = write!(out, "nil");
+            } else {
+                let _ = write!(out, "{}", (*right).val);
+            }
+
+            println!("{}", out);
+        };
+        unsafe { self.traversal_preorder(self.root, dump) }
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> Default for RbTree<Key, T, A> {
+    fn default() -> Self {
+        Self::new_augmented()
+    }
+}
+
+impl<Key: PartialOrd, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    // first node in order whose key is above key, or equal to it if inclusive
+    fn first_above(&self, key: &Key, inclusive: bool) -> *mut RbNode<Key, T> {
+        let mut p = self.root;
+        let mut found = null_mut::<RbNode<Key, T>>();
+
+        while !p.is_null() {
+            unsafe {
+                let ord = (*p).val.cmp_key(key);
+                if ord == Some(Ordering::Greater) || (inclusive && ord == Some(Ordering::Equal)) {
+                    found = p;
+                    p = (*p).get_child(NodeDirection::LeftChild);
+                } else {
+                    p = (*p).get_child(NodeDirection::RightChild);
+                }
+            }
+        }
+
+        found
+    }
+
+    // last node in order whose key is below key, or equal to it if inclusive
+    fn last_below(&self, key: &Key, inclusive: bool) -> *mut RbNode<Key, T> {
+        let mut p = self.root;
+        let mut found = null_mut::<RbNode<Key, T>>();
+
+        while !p.is_null() {
+            unsafe {
+                let ord = (*p).val.cmp_key(key);
+                if ord == Some(Ordering::Less) || (inclusive && ord == Some(Ordering::Equal)) {
+                    found = p;
+                    p = (*p).get_child(NodeDirection::RightChild);
+                } else {
+                    p = (*p).get_child(NodeDirection::LeftChild);
+                }
+            }
+        }
+
+        found
+    }
+
+    // equal keys may be spread over both subtrees, the first one in order is
+    // returned
+    fn find_node(&self, key: &Key) -> *mut RbNode<Key, T> {
+        let node = self.first_above(key, true);
+        if !node.is_null() && unsafe { (*node).val.cmp_key(key) } == Some(Ordering::Equal) {
+            node
+        } else {
+            null_mut()
+        }
+    }
+
+    pub fn find(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        unsafe { self.find_node(key).as_ref() }
+    }
+
+    pub fn find_mut(&mut self, key: &Key) -> Option<&mut RbNode<Key, T>> {
+        unsafe { self.find_node(key).as_mut() }
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        !self.find_node(key).is_null()
+    }
+
+    /// First node with a key `>= key`.
+    pub fn lower_bound(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        unsafe { self.first_above(key, true).as_ref() }
+    }
+
+    /// First node with a key `> key`.
+    pub fn upper_bound(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        unsafe { self.first_above(key, false).as_ref() }
+    }
+
+    /// Last node with a key `<= key`.
+    pub fn floor(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        unsafe { self.last_below(key, true).as_ref() }
+    }
+
+    /// Last node with a key `< key`.
+    pub fn lower(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        unsafe { self.last_below(key, false).as_ref() }
+    }
+
+    /// Looks `key` up once, remembering where a node for it would be linked
+    /// when it is absent.
+    pub fn entry(&mut self, key: Key) -> Entry<'_, Key, T, A> {
+        let mut parent = null_mut::<RbNode<Key, T>>();
+        let mut di = NodeDirection::LeftChild;
+        let mut found = null_mut::<RbNode<Key, T>>();
+        let mut p = self.root;
+
+        while !p.is_null() {
+            unsafe {
+                let ord = (*p).val.cmp_key(&key);
+                if ord == Some(Ordering::Less) {
+                    di = NodeDirection::RightChild;
+                } else {
+                    if ord == Some(Ordering::Equal) {
+                        found = p;
+                    }
+                    di = NodeDirection::LeftChild;
+                }
+                parent = p;
+                p = (*p).get_child(di);
+            }
+        }
+
+        if found.is_null() {
+            Entry::Vacant(VacantEntry::new(self, key, parent, di))
+        } else {
+            Entry::Occupied(OccupiedEntry::new(self, found))
+        }
+    }
+
+    /// Cursor at the first node whose key equals `key`, or at the ghost
+    /// position if there is none.
+    pub fn cursor_at(&mut self, key: &Key) -> CursorMut<'_, Key, T, A> {
+        let current = self.find_node(key);
+        CursorMut::new(self, current)
+    }
+
+    // both ends of the nodes covered by range, or nulls when it is empty
+    fn range_ends<R: RangeBounds<Key>>(
+        &self,
+        range: &R,
+    ) -> (*mut RbNode<Key, T>, *mut RbNode<Key, T>) {
+        let head = match range.start_bound() {
+            Bound::Included(k) => self.first_above(k, true),
+            Bound::Excluded(k) => self.first_above(k, false),
+            Bound::Unbounded => RbNode::extreme(self.root, NodeDirection::LeftChild),
+        };
+        let tail = match range.end_bound() {
+            Bound::Included(k) => self.last_below(k, true),
+            Bound::Excluded(k) => self.last_below(k, false),
+            Bound::Unbounded => RbNode::extreme(self.root, NodeDirection::RightChild),
+        };
+
+        if head.is_null() || tail.is_null() || unsafe { (*head).val > (*tail).val } {
+            (null_mut(), null_mut())
+        } else {
+            (head, tail)
+        }
+    }
+
+    pub fn range<R: RangeBounds<Key>>(&self, range: R) -> Range<'_, Key, T> {
+        let (head, tail) = self.range_ends(&range);
+        Range::new(head, tail)
+    }
+
+    pub fn range_mut<R: RangeBounds<Key>>(&mut self, range: R) -> RangeMut<'_, Key, T> {
+        let (head, tail) = self.range_ends(&range);
+        RangeMut::new(head, tail)
+    }
+
+    /// Same as [`RbTree::lower_bound`].
+    pub fn ceiling(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        self.lower_bound(key)
     }
 }
 
@@ -561,7 +1008,7 @@ mod test {
     use crate::utils;
 
     #[derive(PartialOrd, PartialEq)]
-    struct Test {
+    pub(crate) struct Test {
         key: i32,
     }
 
@@ -585,7 +1032,100 @@ mod test {
         }
     }
 
+    // ordered by key only, sum keeps the total weight of the subtree; the
+    // weight follows the key unless changed after setting it
+    pub(crate) struct Summed {
+        key: i32,
+        pub(crate) weight: i64,
+        pub(crate) sum: i64,
+    }
+
+    impl PartialEq for Summed {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl PartialOrd for Summed {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            self.key.partial_cmp(&other.key)
+        }
+    }
+
+    impl RbTrait<i32> for Summed {
+        fn new(key: i32) -> Self {
+            Summed {
+                key,
+                weight: key as i64,
+                sum: 0,
+            }
+        }
+
+        fn get(&self) -> i32 {
+            self.key
+        }
+
+        fn set(&mut self, key: i32) {
+            self.key = key;
+            self.weight = key as i64;
+        }
+    }
+
+    pub(crate) struct SumAugment;
+
+    fn sum(node: &RbNode<i32, Summed>) -> i64 {
+        node.val().weight
+            + node.left().map_or(0, |n| n.val().sum)
+            + node.right().map_or(0, |n| n.val().sum)
+    }
+
+    impl RbAugment<i32, Summed> for SumAugment {
+        fn compute(node: &mut RbNode<i32, Summed>) -> bool {
+            let sum = sum(node);
+            let changed = node.val().sum != sum;
+            node.val_mut().sum = sum;
+            changed
+        }
+
+        fn verify(node: &RbNode<i32, Summed>) -> bool {
+            node.val().sum == sum(node)
+        }
+    }
+
+    // ordered by key only, tag tells apart duplicates
+    struct Tagged {
+        key: i32,
+        tag: usize,
+    }
+
+    impl RbTrait<i32> for Tagged {
+        fn new(key: i32) -> Self {
+            Tagged { key, tag: 0 }
+        }
+
+        fn get(&self) -> i32 {
+            self.key
+        }
+
+        fn set(&mut self, key: i32) {
+            self.key = key
+        }
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            self.key.partial_cmp(&other.key)
+        }
+    }
+
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn stress() {
         let mut array = [0i32; 1000];
         let mut tree = RbTree::<i32, Test>::new();
@@ -595,16 +1135,520 @@ mod test {
         let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 1000);
         for i in 0..1000 {
             nodes[i].set(array[i]);
-            tree.insert(&mut nodes[i]);
+            tree.insert(&mut nodes[i]).unwrap();
         }
 
         for i in 0..1000 {
-            tree.delete(&mut nodes[i]);
+            tree.delete(&mut nodes[i]).unwrap();
+        }
+    }
+
+    #[test]
+    fn find() {
+        let mut nodes: [RbNode<i32, Test>; 64] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new(i as i32 * 2));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        assert!(tree.find(&0).is_none());
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        for i in 0..128 {
+            assert_eq!(tree.contains(&i), i % 2 == 0);
+            if i % 2 == 0 {
+                assert_eq!(tree.find(&i).unwrap().val().get(), i);
+            }
+        }
+
+        tree.find_mut(&126).unwrap().val_mut().set(127);
+        assert!(!tree.contains(&126));
+        assert!(tree.contains(&127));
+        assert!(tree.verify_tree());
+    }
+
+    #[test]
+    fn bounds() {
+        let mut array = [0i32; 200];
+        let mut tree = RbTree::<i32, Test>::new();
+        let mut nodes: [RbNode<i32, Test>; 200] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 200);
+        let mut model: Vec<i32> = array.iter().map(|x| x.rem_euclid(100)).collect();
+        for (node, key) in nodes.iter_mut().zip(model.iter()) {
+            node.set(*key);
+            tree.insert(node).unwrap();
+        }
+        model.sort();
+
+        let key = |node: Option<&RbNode<i32, Test>>| node.map(|n| n.val().get());
+        for k in -5..105 {
+            let ge = model.iter().find(|&&x| x >= k).copied();
+            let gt = model.iter().find(|&&x| x > k).copied();
+            let le = model.iter().rev().find(|&&x| x <= k).copied();
+            let lt = model.iter().rev().find(|&&x| x < k).copied();
+
+            assert_eq!(key(tree.lower_bound(&k)), ge);
+            assert_eq!(key(tree.ceiling(&k)), ge);
+            assert_eq!(key(tree.upper_bound(&k)), gt);
+            assert_eq!(key(tree.floor(&k)), le);
+            assert_eq!(key(tree.lower(&k)), lt);
+        }
+    }
+
+    #[test]
+    fn iter() {
+        let mut array = [0i32; 200];
+        let mut tree = RbTree::<i32, Test>::new();
+        let mut nodes: [RbNode<i32, Test>; 200] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+
+        assert!(tree.iter().next().is_none());
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 200);
+        let mut model: Vec<i32> = array.iter().map(|x| x.rem_euclid(100)).collect();
+        for (node, key) in nodes.iter_mut().zip(model.iter()) {
+            node.set(*key);
+            tree.insert(node).unwrap();
+        }
+        model.sort();
+
+        let keys: Vec<i32> = tree.iter().map(|v| v.get()).collect();
+        assert_eq!(keys, model);
+        let keys: Vec<i32> = tree.iter().rev().map(|v| v.get()).collect();
+        assert_eq!(keys, model.iter().rev().copied().collect::<Vec<_>>());
+
+        let mut it = tree.iter();
+        let mut front = 0;
+        let mut back = model.len();
+        while let Some(v) = it.next() {
+            assert_eq!(v.get(), model[front]);
+            front += 1;
+            if let Some(v) = it.next_back() {
+                back -= 1;
+                assert_eq!(v.get(), model[back]);
+            }
+        }
+        assert_eq!(front, back);
+
+        for v in &mut tree {
+            v.set(v.get() * 2);
+        }
+        let keys: Vec<i32> = (&tree).into_iter().map(|v| v.get()).collect();
+        assert_eq!(keys, model.iter().map(|x| x * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn navigation() {
+        let mut nodes: [RbNode<i32, Test>; 100] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new((i as i32 * 37) % 100));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        assert!(tree.first().is_none());
+        assert!(tree.last().is_none());
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        let mut node = tree.first();
+        for i in 0..100 {
+            assert_eq!(node.unwrap().val().get(), i);
+            node = node.unwrap().next();
+        }
+        assert!(node.is_none());
+
+        let mut node = tree.last();
+        for i in (0..100).rev() {
+            assert_eq!(node.unwrap().val().get(), i);
+            node = node.unwrap().prev();
+        }
+        assert!(node.is_none());
+
+        let mid = tree.find(&50).unwrap();
+        assert_eq!(mid.next().unwrap().val().get(), 51);
+        assert_eq!(mid.prev().unwrap().val().get(), 49);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut nodes: [RbNode<i32, Test>; 100] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new(i as i32 * 2));
+        let mut extra: [RbNode<i32, Test>; 4] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        // drop every key divisible by 4
+        let mut cursor = tree.cursor_front_mut();
+        while let Some(v) = cursor.current() {
+            if v.get() % 4 == 0 {
+                let node = cursor.remove_current().unwrap();
+                assert_eq!(node.val().get() % 4, 0);
+            } else {
+                cursor.move_next();
+            }
+        }
+        // past the end is the ghost, moving on wraps to the front
+        cursor.move_next();
+        assert_eq!(cursor.current().unwrap().get(), 2);
+        cursor.move_prev();
+        assert!(cursor.current().is_none());
+        cursor.move_prev();
+        assert_eq!(cursor.current().unwrap().get(), 198);
+
+        let keys: Vec<i32> = tree.iter().map(|v| v.get()).collect();
+        assert_eq!(keys, (0..50).map(|i| i * 4 + 2).collect::<Vec<_>>());
+
+        let [a, b, c, d] = &mut extra;
+        let mut cursor = tree.cursor_at(&50);
+        a.set(51);
+        cursor.insert_after(a).unwrap();
+        b.set(49);
+        cursor.insert_before(b).unwrap();
+        assert_eq!(cursor.insert_after(a).err(), Some(RbError::AlreadyLinked));
+        assert_eq!(cursor.current().unwrap().get(), 50);
+        assert_eq!(cursor.peek_next().unwrap().get(), 51);
+        assert_eq!(cursor.peek_prev().unwrap().get(), 49);
+
+        let mut cursor = tree.cursor_at(&51);
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current().unwrap().get(), 49);
+
+        let mut cursor = tree.cursor_at(&1);
+        assert!(cursor.current().is_none());
+        c.set(0);
+        cursor.insert_after(c).unwrap();
+        d.set(1000);
+        cursor.insert_before(d).unwrap();
+        assert_eq!(tree.first().unwrap().val().get(), 0);
+        assert_eq!(tree.last().unwrap().val().get(), 1000);
+        assert!(tree.verify_tree());
+    }
+
+    #[test]
+    fn range() {
+        let mut array = [0i32; 200];
+        let mut tree = RbTree::<i32, Test>::new();
+        let mut nodes: [RbNode<i32, Test>; 200] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+
+        assert!(tree.range(..).next().is_none());
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 200);
+        let mut model: Vec<i32> = array.iter().map(|x| x.rem_euclid(100)).collect();
+        for (node, key) in nodes.iter_mut().zip(model.iter()) {
+            node.set(*key);
+            tree.insert(node).unwrap();
+        }
+        model.sort();
+
+        let keys = |r: Range<'_, i32, Test>| r.map(|v| v.get()).collect::<Vec<_>>();
+        let expect =
+            |f: &dyn Fn(i32) -> bool| model.iter().copied().filter(|&x| f(x)).collect::<Vec<_>>();
+        for a in (-5..105).step_by(7) {
+            for b in (-5..105).step_by(5) {
+                assert_eq!(keys(tree.range(a..b)), expect(&|x| a <= x && x < b));
+                assert_eq!(keys(tree.range(a..=b)), expect(&|x| a <= x && x <= b));
+                assert_eq!(
+                    keys(tree.range((Bound::Excluded(a), Bound::Excluded(b)))),
+                    expect(&|x| a < x && x < b)
+                );
+            }
+            assert_eq!(keys(tree.range(a..)), expect(&|x| a <= x));
+            assert_eq!(keys(tree.range(..a)), expect(&|x| x < a));
+        }
+        assert_eq!(keys(tree.range(..)), model);
+
+        let rev: Vec<i32> = tree.range(20..80).rev().map(|v| v.get()).collect();
+        let mut want = expect(&|x| (20..80).contains(&x));
+        want.reverse();
+        assert_eq!(rev, want);
+
+        for v in tree.range_mut(50..) {
+            v.set(v.get() + 1000);
+        }
+        assert_eq!(
+            tree.iter().map(|v| v.get()).collect::<Vec<_>>(),
+            model
+                .iter()
+                .map(|&x| if x >= 50 { x + 1000 } else { x })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn entry() {
+        let mut nodes: [RbNode<i32, Test>; 64] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        let mut free = nodes.iter_mut();
+        for i in 0..128 {
+            let key = (i * 29) % 64;
+            match tree.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    assert!(i >= 64);
+                    assert_eq!(entry.get().get(), key);
+                    entry.get_mut().set(key);
+                }
+                Entry::Vacant(entry) => {
+                    assert!(i < 64);
+                    assert_eq!(*entry.key(), key);
+                    assert_eq!(entry.insert(free.next().unwrap()).unwrap().get(), key);
+                }
+            }
+        }
+        assert!(tree.iter().map(|v| v.get()).eq(0..64));
+
+        match tree.entry(10) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove().val().get(), 10),
+            Entry::Vacant(_) => panic!("10 should be present"),
+        }
+        assert!(!tree.contains(&10));
+        assert!(matches!(tree.entry(10), Entry::Vacant(_)));
+        assert!(tree.verify_tree());
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut nodes: [RbNode<i32, Tagged>; 120] = std::array::from_fn(|i| {
+            let mut node = RbNode::<i32, Tagged>::new((i as i32 * 7) % 10);
+            node.val_mut().tag = i;
+            node
+        });
+        let mut tree = RbTree::<i32, Tagged>::new();
+
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        // multiset mode keeps equal keys in insertion order
+        let order: Vec<(i32, usize)> = tree.iter().map(|v| (v.key, v.tag)).collect();
+        let mut expect: Vec<(i32, usize)> = (0..120).map(|i| ((i as i32 * 7) % 10, i)).collect();
+        expect.sort_by_key(|&(key, _)| key);
+        assert_eq!(order, expect);
+        assert_eq!(tree.find(&3).unwrap().val().tag, expect[36].1);
+    }
+
+    #[test]
+    fn insert_unique() {
+        let mut nodes: [RbNode<i32, Tagged>; 40] = std::array::from_fn(|i| {
+            let mut node = RbNode::<i32, Tagged>::new(i as i32 % 20);
+            node.val_mut().tag = i;
+            node
+        });
+        let mut tree = RbTree::<i32, Tagged>::new();
+
+        for node in nodes.iter_mut() {
+            let tag = node.val().tag;
+            match tree.insert_unique(node) {
+                Ok(_) => assert!(tag < 20),
+                Err(UniqueError::Exists(existing)) => {
+                    assert!(tag >= 20);
+                    assert_eq!(existing.val().tag, tag - 20);
+                }
+                Err(UniqueError::AlreadyLinked) => panic!("node {} is linked", tag),
+            }
+        }
+        assert!(matches!(
+            tree.insert_unique(&mut nodes[3]),
+            Err(UniqueError::AlreadyLinked)
+        ));
+
+        assert!(tree.iter().map(|v| v.tag).eq(0..20));
+        assert!(tree.verify_tree());
+    }
+
+    #[test]
+    fn replace() {
+        let mut nodes: [RbNode<i32, Tagged>; 50] =
+            std::array::from_fn(|i| RbNode::<i32, Tagged>::new(i as i32));
+        let mut spare: [RbNode<i32, Tagged>; 50] = std::array::from_fn(|i| {
+            let mut node = RbNode::<i32, Tagged>::new(i as i32);
+            node.val_mut().tag = 1;
+            node
+        });
+        let mut tree = RbTree::<i32, Tagged>::new();
+
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        // covers the root, inner nodes and leaves
+        for (old, new) in nodes.iter_mut().zip(spare.iter_mut()).step_by(3) {
+            tree.replace(old, new).unwrap();
+        }
+
+        let tags: Vec<(i32, usize)> = tree.iter().map(|v| (v.key, v.tag)).collect();
+        let expect: Vec<(i32, usize)> = (0..50).map(|i| (i, (i % 3 == 0) as usize)).collect();
+        assert_eq!(tags, expect);
+
+        for (old, new) in nodes.iter_mut().zip(spare.iter_mut()).step_by(3) {
+            tree.replace(new, old).unwrap();
+        }
+        assert!(tree.iter().all(|v| v.tag == 0));
+    }
+
+    #[test]
+    fn membership() {
+        let mut nodes: [RbNode<i32, Test>; 20] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new(i as i32));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        assert!(!nodes[0].is_linked());
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+        assert!(nodes.iter().all(|n| n.is_linked()));
+        assert_eq!(
+            tree.insert(&mut nodes[7]).err(),
+            Some(RbError::AlreadyLinked)
+        );
+
+        tree.delete(&mut nodes[7]).unwrap();
+        assert!(!nodes[7].is_linked());
+        assert_eq!(tree.delete(&mut nodes[7]).err(), Some(RbError::NotLinked));
+        assert!(tree.verify_tree());
+
+        // popped and replaced nodes come out unlinked as well
+        let first: *const RbNode<i32, Test> = tree.pop_first().unwrap();
+        assert!(!unsafe { &*first }.is_linked());
+        let mut spare = RbNode::<i32, Test>::new(7);
+        tree.replace(&mut nodes[8], &mut spare).unwrap();
+        assert!(!nodes[8].is_linked() && spare.is_linked());
+        assert_eq!(
+            tree.replace(&mut nodes[8], &mut RbNode::new(8)).err(),
+            Some(RbError::NotLinked)
+        );
+        assert_eq!(
+            tree.replace(&mut nodes[9], &mut spare).err(),
+            Some(RbError::AlreadyLinked)
+        );
+
+        // a node of one tree can't be taken out through another
+        let mut other = RbTree::<i32, Test>::new();
+        let mut alone = RbNode::<i32, Test>::new(100);
+        other.insert(&mut alone).unwrap();
+        if cfg!(debug_assertions) {
+            assert_eq!(tree.delete(&mut alone).err(), Some(RbError::WrongTree));
+            assert_eq!(other.delete(&mut nodes[3]).err(), Some(RbError::WrongTree));
+        }
+        other.delete(&mut alone).unwrap();
+
+        tree.clear();
+        assert!(nodes.iter().all(|n| !n.is_linked()) && !spare.is_linked());
+
+        // a dropped tree leaves its nodes linked until they are reset
+        {
+            let mut gone = RbTree::<i32, Test>::new();
+            gone.insert(&mut nodes[0]).unwrap();
+        }
+        assert_eq!(
+            tree.insert(&mut nodes[0]).err(),
+            Some(RbError::AlreadyLinked)
+        );
+        unsafe { nodes[0].reset() };
+        tree.insert(&mut nodes[0]).unwrap();
+    }
+
+    #[test]
+    fn augment() {
+        let mut array = [0i32; 300];
+        let mut tree = RbTree::<i32, Summed, SumAugment>::new_augmented();
+        let mut nodes: [RbNode<i32, Summed>; 300] =
+            std::array::from_fn(|_| RbNode::<i32, Summed>::new(0));
+        let mut spare = RbNode::<i32, Summed>::new(0);
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 300);
+        for (node, key) in nodes.iter_mut().zip(array.iter()) {
+            node.set(key.rem_euclid(1000));
+            tree.insert(node).unwrap();
+        }
+        let total = |tree: &RbTree<i32, Summed, SumAugment>| unsafe {
+            tree.root.as_ref().map_or(0, |n| n.val().sum)
+        };
+        let mut expect: i64 = nodes.iter().map(|n| n.val().key as i64).sum();
+        assert_eq!(total(&tree), expect);
+
+        spare.set(nodes[7].val().key);
+        tree.replace(&mut nodes[7], &mut spare).unwrap();
+        assert_eq!(total(&tree), expect);
+        tree.replace(&mut spare, &mut nodes[7]).unwrap();
+
+        // same key, other weight, the ancestors pick up the difference
+        spare.set(nodes[7].val().key);
+        spare.val_mut().weight += 1000;
+        tree.replace(&mut nodes[7], &mut spare).unwrap();
+        assert_eq!(total(&tree), expect + 1000);
+        assert!(tree.verify_tree());
+        tree.replace(&mut spare, &mut nodes[7]).unwrap();
+        assert_eq!(total(&tree), expect);
+
+        for node in nodes.iter_mut().step_by(2) {
+            expect -= node.val().key as i64;
+            tree.delete(node).unwrap();
+            assert_eq!(total(&tree), expect);
+        }
+        assert!(tree.verify_tree());
+
+        // a stale summary is reported, and left as it was
+        unsafe { (*tree.root).val.sum += 1 };
+        assert!(!tree.verify_tree());
+        assert!(!tree.verify_tree());
+        unsafe { (*tree.root).val.sum -= 1 };
+        assert!(tree.verify_tree());
+    }
+
+    #[test]
+    fn pop() {
+        let mut nodes: [RbNode<i32, Test>; 50] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new((i as i32 * 13) % 50));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        assert!(tree.peek_first().is_none());
+        assert!(tree.pop_last().is_none());
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        for i in 0..25 {
+            assert_eq!(tree.peek_first().unwrap().get(), i);
+            assert_eq!(tree.peek_last().unwrap().get(), 49 - i);
+            assert_eq!(tree.pop_first().unwrap().val().get(), i);
+            assert_eq!(tree.pop_last().unwrap().val().get(), 49 - i);
+        }
+        assert!(tree.pop_first().is_none());
+        assert!(tree.peek_last().is_none());
+    }
+
+    #[test]
+    fn from_sorted() {
+        for len in 0..70 {
+            let mut nodes: Vec<RbNode<i32, Summed>> = (0..len)
+                .map(|i| RbNode::<i32, Summed>::new(i / 2))
+                .collect();
+            let mut tree =
+                RbTree::<i32, Summed, SumAugment>::from_sorted_nodes(nodes.iter_mut()).unwrap();
+
+            assert!(tree.verify_tree());
+            assert!(tree.iter().map(|v| v.key).eq((0..len).map(|i| i / 2)));
+
+            let mut extra = RbNode::<i32, Summed>::new(len / 3);
+            tree.insert(&mut extra).unwrap();
+            for node in nodes.iter_mut().step_by(2) {
+                tree.delete(node).unwrap();
+            }
+            tree.delete(&mut extra).unwrap();
         }
     }
 
     #[test]
-    fn debug() {
+    fn debug() -> Result<(), RbError> {
         let mut a = RbNode::<i32, Test>::new(1);
         let mut b = RbNode::<i32, Test>::new(3);
         let mut c = RbNode::<i32, Test>::new(8);
@@ -621,38 +1665,39 @@ mod test {
 
         let mut tree = RbTree::<i32, Test>::new();
 
-        tree.insert(&mut a)
-            .insert(&mut b)
-            .insert(&mut c)
-            .insert(&mut d)
-            .insert(&mut e)
-            .insert(&mut f)
-            .insert(&mut g)
-            .insert(&mut h)
-            .insert(&mut i)
-            .insert(&mut j)
-            .insert(&mut k)
-            .insert(&mut l)
-            .insert(&mut m);
+        tree.insert(&mut a)?
+            .insert(&mut b)?
+            .insert(&mut c)?
+            .insert(&mut d)?
+            .insert(&mut e)?
+            .insert(&mut f)?
+            .insert(&mut g)?
+            .insert(&mut h)?
+            .insert(&mut i)?
+            .insert(&mut j)?
+            .insert(&mut k)?
+            .insert(&mut l)?
+            .insert(&mut m)?;
 
         assert!(tree.verify_tree());
 
         tree.dump_tree();
 
-        tree.delete(&mut a)
-            .delete(&mut d)
-            .delete(&mut h)
-            .delete(&mut m)
-            .delete(&mut k)
-            .delete(&mut b)
-            .delete(&mut i)
-            .delete(&mut c)
-            .delete(&mut j)
-            .delete(&mut l)
-            .delete(&mut e)
-            .delete(&mut g)
-            .delete(&mut f);
+        tree.delete(&mut a)?
+            .delete(&mut d)?
+            .delete(&mut h)?
+            .delete(&mut m)?
+            .delete(&mut k)?
+            .delete(&mut b)?
+            .delete(&mut i)?
+            .delete(&mut c)?
+            .delete(&mut j)?
+            .delete(&mut l)?
+            .delete(&mut e)?
+            .delete(&mut g)?
+            .delete(&mut f)?;
 
         tree.dump_tree();
+        Ok(())
     }
 }
@@ -0,0 +1,368 @@
+use crate::{RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+
+// the tree value of a map node, value is only None between RbNode::new and
+// the map filling it in
+pub(crate) struct MapEntry<K, V> {
+    key: K,
+    value: Option<V>,
+}
+
+impl<K: Clone, V> RbTrait<K> for MapEntry<K, V> {
+    fn new(key: K) -> Self {
+        MapEntry { key, value: None }
+    }
+
+    fn get(&self) -> K {
+        self.key.clone()
+    }
+
+    fn set(&mut self, key: K) {
+        self.key = key
+    }
+
+    fn cmp_key(&self, key: &K) -> Option<Ordering>
+    where
+        K: PartialOrd,
+    {
+        self.key.partial_cmp(key)
+    }
+}
+
+impl<K: PartialEq, V> PartialEq for MapEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for MapEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+type MapNode<K, V> = RbNode<K, MapEntry<K, V>>;
+
+/// An ordered map owning its nodes, built on the intrusive [`RbTree`].
+pub struct RbMap<K, V> {
+    tree: RbTree<K, MapEntry<K, V>>,
+    len: usize,
+}
+
+unsafe impl<K: Send, V: Send> Send for RbMap<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for RbMap<K, V> {}
+
+impl<K: Ord + Clone, V> RbMap<K, V> {
+    pub fn new() -> Self {
+        RbMap {
+            tree: RbTree::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.tree.find(key).and_then(|n| n.val.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.tree.find_mut(key).and_then(|n| n.val.value.as_mut())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.tree.contains(key)
+    }
+
+    /// Inserts `value` under `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let node = self.tree.find_mut(key)? as *mut MapNode<K, V>;
+        self.len -= 1;
+        unsafe {
+            self.tree.unlink(&mut *node);
+            Box::from_raw(node).val.value
+        }
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let len = &mut self.len;
+        match self.tree.entry(key) {
+            crate::Entry::Occupied(inner) => Entry::Occupied(OccupiedEntry { inner, len }),
+            crate::Entry::Vacant(inner) => Entry::Vacant(VacantEntry { inner, len }),
+        }
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.tree.first().map(|n| n.val.pair())
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.tree.last().map(|n| n.val.pair())
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.tree.iter(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.free_nodes();
+    }
+}
+
+impl<K, V> MapEntry<K, V> {
+    fn pair(&self) -> (&K, &V) {
+        (&self.key, self.value.as_ref().unwrap())
+    }
+}
+
+impl<K: Ord + Clone, V> Default for RbMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> RbMap<K, V> {
+    fn free_nodes(&mut self) {
+        self.tree
+            .drain_postorder(|node| drop(unsafe { Box::from_raw(node) }));
+        self.len = 0;
+    }
+}
+
+impl<K, V> Drop for RbMap<K, V> {
+    fn drop(&mut self) {
+        self.free_nodes();
+    }
+}
+
+impl<'a, K: Ord + Clone, V> IntoIterator for &'a RbMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    inner: crate::Iter<'a, K, MapEntry<K, V>>,
+}
+
+impl<'a, K: Clone + PartialOrd, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        self.inner.next().map(|e| e.pair())
+    }
+}
+
+impl<'a, K: Clone + PartialOrd, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        self.inner.next_back().map(|e| e.pair())
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    inner: crate::OccupiedEntry<'a, K, MapEntry<K, V>>,
+    len: &'a mut usize,
+}
+
+pub struct VacantEntry<'a, K, V> {
+    inner: crate::VacantEntry<'a, K, MapEntry<K, V>>,
+    len: &'a mut usize,
+}
+
+impl<'a, K: Ord + Clone, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut())
+        }
+        self
+    }
+}
+
+impl<'a, K: Ord + Clone, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.inner.get().key
+    }
+
+    pub fn get(&self) -> &V {
+        self.inner.get().value.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.inner.get_mut().value.as_mut().unwrap()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.inner.into_mut().value.as_mut().unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        let node = self.inner.remove() as *mut MapNode<K, V>;
+        *self.len -= 1;
+        let node = unsafe { Box::from_raw(node) };
+        node.val.value.unwrap()
+    }
+}
+
+impl<'a, K: Ord + Clone, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.len += 1;
+        let val = self.inner.insert_with(|key| {
+            let node = Box::leak(Box::new(MapNode::new(key)));
+            node.val.value = Some(value);
+            node
+        });
+        val.value.as_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn against_btreemap() {
+        let mut array = [0i32; 600];
+        let mut map = RbMap::<i32, u32>::new();
+        let mut model = BTreeMap::<i32, u32>::new();
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 600);
+        for (i, x) in array.iter().enumerate() {
+            let key = x.rem_euclid(150);
+            match x.rem_euclid(3) {
+                0 => assert_eq!(map.remove(&key), model.remove(&key)),
+                _ => assert_eq!(map.insert(key, i as u32), model.insert(key, i as u32)),
+            }
+            assert_eq!(map.len(), model.len());
+        }
+
+        for key in 0..150 {
+            assert_eq!(map.get(&key), model.get(&key));
+            assert_eq!(map.contains_key(&key), model.contains_key(&key));
+        }
+        assert!(map.iter().eq(model.iter()));
+        assert!(map.iter().rev().eq(model.iter().rev()));
+        assert_eq!(map.first_key_value(), model.first_key_value());
+        assert_eq!(map.last_key_value(), model.last_key_value());
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.iter().next().is_none());
+    }
+
+    #[test]
+    fn entry() {
+        let mut map = RbMap::<String, usize>::new();
+        for word in "a b a c b a".split(' ') {
+            *map.entry(word.to_string()).or_default() += 1;
+        }
+
+        let counts: Vec<(&str, usize)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(counts, [("a", 3), ("b", 2), ("c", 1)]);
+
+        map.entry("c".to_string())
+            .and_modify(|v| *v = 10)
+            .or_insert(0);
+        map.entry("d".to_string())
+            .and_modify(|v| *v = 10)
+            .or_insert(4);
+        assert_eq!(map.get(&"c".to_string()), Some(&10));
+        assert_eq!(map.get(&"d".to_string()), Some(&4));
+
+        if let Entry::Occupied(entry) = map.entry("a".to_string()) {
+            assert_eq!(entry.remove(), 3);
+        } else {
+            panic!("a should be present");
+        }
+        assert_eq!(map.len(), 3);
+        assert!(!map.contains_key(&"a".to_string()));
+    }
+
+    // a key the map must never copy
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Strict(i32);
+
+    impl Clone for Strict {
+        fn clone(&self) -> Self {
+            panic!("key {} cloned", self.0)
+        }
+    }
+
+    #[test]
+    fn keys_not_cloned() {
+        let mut map = RbMap::<Strict, i32>::new();
+        for i in 0..64 {
+            assert_eq!(map.insert(Strict(i * 7 % 64), i), None);
+        }
+        assert_eq!(map.insert(Strict(5), -1), Some(19));
+        *map.entry(Strict(6)).or_default() += 100;
+
+        for i in 0..64 {
+            assert!(map.contains_key(&Strict(i)));
+        }
+        assert_eq!(map.remove(&Strict(6)), Some(110));
+        assert_eq!(map.remove(&Strict(6)), None);
+        assert_eq!(map.get(&Strict(5)), Some(&-1));
+        assert_eq!(map.len(), 63);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{RbAugment, RbNode, RbTrait, RbTree};
+use std::cmp::Ordering;
+
+/// Values carrying the number of nodes in their subtree, maintained by
+/// [`SubtreeSize`].
+pub trait RbCounted {
+    fn subtree_size(&self) -> usize;
+    fn set_subtree_size(&mut self, size: usize);
+}
+
+/// The augmentation behind [`RbTree::select`] and [`RbTree::rank`].
+pub struct SubtreeSize;
+
+fn count<Key, T: RbTrait<Key> + PartialOrd + RbCounted>(node: Option<&RbNode<Key, T>>) -> usize {
+    node.map_or(0, |n| n.val.subtree_size())
+}
+
+fn size<Key, T: RbTrait<Key> + PartialOrd + RbCounted>(node: &RbNode<Key, T>) -> usize {
+    1 + count(node.left()) + count(node.right())
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd + RbCounted> RbAugment<Key, T> for SubtreeSize {
+    fn compute(node: &mut RbNode<Key, T>) -> bool {
+        let size = size(node);
+        let changed = node.val.subtree_size() != size;
+        node.val.set_subtree_size(size);
+        changed
+    }
+
+    fn verify(node: &RbNode<Key, T>) -> bool {
+        node.val.subtree_size() == size(node)
+    }
+
+    fn copy(old: &RbNode<Key, T>, new: &mut RbNode<Key, T>) {
+        new.val.set_subtree_size(old.val.subtree_size())
+    }
+
+    fn rotate(old: &mut RbNode<Key, T>, new: &mut RbNode<Key, T>) {
+        new.val.set_subtree_size(old.val.subtree_size());
+        Self::compute(old);
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd + RbCounted> RbTree<Key, T, SubtreeSize> {
+    pub fn len(&self) -> usize {
+        count(unsafe { self.root.as_ref() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_null()
+    }
+
+    /// The node at in-order position `index`, counting from 0.
+    pub fn select(&self, mut index: usize) -> Option<&RbNode<Key, T>> {
+        let mut node = unsafe { self.root.as_ref() };
+        while let Some(n) = node {
+            let left = count(n.left());
+            if index < left {
+                node = n.left();
+            } else if index == left {
+                return Some(n);
+            } else {
+                index -= left + 1;
+                node = n.right();
+            }
+        }
+        None
+    }
+}
+
+impl<Key: PartialOrd, T: RbTrait<Key> + PartialOrd + RbCounted> RbTree<Key, T, SubtreeSize> {
+    /// How many nodes have a key below `key`.
+    pub fn rank(&self, key: &Key) -> usize {
+        let mut rank = 0;
+        let mut node = unsafe { self.root.as_ref() };
+        while let Some(n) = node {
+            if n.val.cmp_key(key) == Some(Ordering::Less) {
+                rank += count(n.left()) + 1;
+                node = n.right();
+            } else {
+                node = n.left();
+            }
+        }
+        rank
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use std::cmp::Ordering;
+
+    struct Counted {
+        key: i32,
+        size: usize,
+    }
+
+    impl RbTrait<i32> for Counted {
+        fn new(key: i32) -> Self {
+            Counted { key, size: 0 }
+        }
+
+        fn get(&self) -> i32 {
+            self.key
+        }
+
+        fn set(&mut self, key: i32) {
+            self.key = key
+        }
+    }
+
+    impl RbCounted for Counted {
+        fn subtree_size(&self) -> usize {
+            self.size
+        }
+
+        fn set_subtree_size(&mut self, size: usize) {
+            self.size = size
+        }
+    }
+
+    impl PartialEq for Counted {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            self.key.partial_cmp(&other.key)
+        }
+    }
+
+    #[test]
+    fn select_rank() {
+        let mut array = [0i32; 300];
+        let mut tree = RbTree::<i32, Counted, SubtreeSize>::new_augmented();
+        let mut nodes: [RbNode<i32, Counted>; 300] =
+            std::array::from_fn(|_| RbNode::<i32, Counted>::new(0));
+
+        assert!(tree.is_empty());
+        assert!(tree.select(0).is_none());
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 300);
+        for (node, key) in nodes.iter_mut().zip(array.iter()) {
+            node.set(key.rem_euclid(500));
+            tree.insert(node).unwrap();
+        }
+        for node in nodes.iter_mut().skip(1).step_by(3) {
+            tree.delete(node).unwrap();
+        }
+
+        let model: Vec<i32> = tree.iter().map(|v| v.key).collect();
+        assert_eq!(tree.len(), 200);
+        assert_eq!(model.len(), 200);
+        for (i, key) in model.iter().enumerate() {
+            assert_eq!(tree.select(i).unwrap().val().key, *key);
+        }
+        assert!(tree.select(200).is_none());
+
+        for key in -1..=501 {
+            let rank = model.iter().filter(|&&x| x < key).count();
+            assert_eq!(tree.rank(&key), rank);
+        }
+    }
+}
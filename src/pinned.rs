@@ -0,0 +1,173 @@
+use crate::{Iter, NoAugment, Range, RbAugment, RbError, RbNode, RbTrait, RbTree};
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::pin::Pin;
+
+/// A safe [`RbTree`] borrowing every linked node for `'a`, so a node can
+/// neither move nor go away while the tree points at it. Removing a node
+/// hands its borrow back.
+///
+/// That guarantee comes from the `'a` borrow, not from `Pin`: an `RbNode`
+/// is `Unpin` whenever `T` is, and only the borrow keeps such a node from
+/// being moved out from under the tree.
+///
+/// ```compile_fail
+/// use rbtree_rust::{RbNode, RbTrait, RbTreePinned};
+///
+/// #[derive(PartialEq, PartialOrd)]
+/// struct Id(u32);
+///
+/// impl RbTrait<u32> for Id {
+///     fn new(key: u32) -> Self { Id(key) }
+///     fn get(&self) -> u32 { self.0 }
+///     fn set(&mut self, key: u32) { self.0 = key }
+/// }
+///
+/// let mut node = Box::pin(RbNode::new(1));
+/// let mut tree = RbTreePinned::<u32, Id>::new();
+/// tree.insert(node.as_mut()).unwrap();
+/// drop(node); // still borrowed by the tree
+/// tree.first();
+/// ```
+pub struct RbTreePinned<'a, Key, T, A = NoAugment> {
+    tree: RbTree<Key, T, A>,
+    marker: PhantomData<Pin<&'a mut RbNode<Key, T>>>,
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd> RbTreePinned<'a, Key, T> {
+    pub fn new() -> Self {
+        Self::new_augmented()
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTreePinned<'a, Key, T, A> {
+    pub fn new_augmented() -> Self {
+        RbTreePinned {
+            tree: RbTree::new_augmented(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, node: Pin<&'a mut RbNode<Key, T>>) -> Result<&mut Self, RbError> {
+        // the node stays in place, the tree only keeps its address
+        self.tree.insert(unsafe { node.get_unchecked_mut() })?;
+        Ok(self)
+    }
+
+    // the borrow of a node the tree just unlinked
+    fn release(node: Option<&mut RbNode<Key, T>>) -> Option<Pin<&'a mut RbNode<Key, T>>> {
+        node.map(|node| unsafe { Pin::new_unchecked(&mut *(node as *mut RbNode<Key, T>)) })
+    }
+
+    /// Unlinks the leftmost node and hands its borrow back.
+    pub fn pop_first(&mut self) -> Option<Pin<&'a mut RbNode<Key, T>>> {
+        Self::release(self.tree.pop_first())
+    }
+
+    /// Unlinks the rightmost node and hands its borrow back.
+    pub fn pop_last(&mut self) -> Option<Pin<&'a mut RbNode<Key, T>>> {
+        Self::release(self.tree.pop_last())
+    }
+
+    pub fn first(&self) -> Option<&RbNode<Key, T>> {
+        self.tree.first()
+    }
+
+    pub fn last(&self) -> Option<&RbNode<Key, T>> {
+        self.tree.last()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Key, T> {
+        self.tree.iter()
+    }
+}
+
+// only shared access is handed out, a node can't be changed in place while
+// the tree orders it
+impl<'a, Key: PartialOrd, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>>
+    RbTreePinned<'a, Key, T, A>
+{
+    pub fn find(&self, key: &Key) -> Option<&RbNode<Key, T>> {
+        self.tree.find(key)
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.tree.contains(key)
+    }
+
+    pub fn range<R: RangeBounds<Key>>(&self, range: R) -> Range<'_, Key, T> {
+        self.tree.range(range)
+    }
+
+    /// Unlinks the first node equal to `key` and hands its borrow back.
+    pub fn remove(&mut self, key: &Key) -> Option<Pin<&'a mut RbNode<Key, T>>> {
+        let node = self.tree.find_node(key);
+        if node.is_null() {
+            return None;
+        }
+
+        unsafe {
+            self.tree.unlink(&mut *node);
+            Self::release(Some(&mut *node))
+        }
+    }
+}
+
+impl<'a, Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> Default
+    for RbTreePinned<'a, Key, T, A>
+{
+    fn default() -> Self {
+        Self::new_augmented()
+    }
+}
+
+// the borrows end with the tree, leave no node pointing into the others
+impl<'a, Key, T, A> Drop for RbTreePinned<'a, Key, T, A> {
+    fn drop(&mut self) {
+        self.tree.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::Test;
+    use std::pin::pin;
+
+    #[test]
+    fn borrow_back() -> Result<(), RbError> {
+        let mut a = pin!(RbNode::<i32, Test>::new(3));
+        let mut b = pin!(RbNode::<i32, Test>::new(1));
+        let mut heap: Vec<Pin<Box<RbNode<i32, Test>>>> =
+            (4..8).map(|key| Box::pin(RbNode::new(key))).collect();
+
+        {
+            let mut tree = RbTreePinned::<i32, Test>::new();
+            tree.insert(a.as_mut())?.insert(b.as_mut())?;
+            for node in heap.iter_mut() {
+                tree.insert(node.as_mut())?;
+            }
+            assert!(tree.iter().map(|v| v.get()).eq([1, 3, 4, 5, 6, 7]));
+
+            let three = tree.remove(&3).unwrap();
+            assert_eq!(three.val().get(), 3);
+            assert!(tree.remove(&3).is_none());
+            assert_eq!(tree.pop_first().unwrap().val().get(), 1);
+            assert_eq!(tree.pop_last().unwrap().val().get(), 7);
+
+            // a node handed back can go in again
+            tree.insert(three)?;
+            assert!(tree.iter().map(|v| v.get()).eq([3, 4, 5, 6]));
+            assert!(tree.range(4..6).map(|v| v.get()).eq([4, 5]));
+            assert_eq!(tree.find(&5).map(|n| n.val().get()), Some(5));
+            assert!(!tree.contains(&7));
+            assert_eq!(tree.last().map(|n| n.val().get()), Some(6));
+        }
+
+        // the tree is gone, so are its borrows
+        a.set(RbNode::new(10));
+        assert_eq!(a.val().get(), 10);
+        drop(heap);
+        Ok(())
+    }
+}
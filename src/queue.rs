@@ -0,0 +1,104 @@
+use crate::{RbError, RbNode, RbTrait, RbTreeCached};
+
+/// A min-first priority queue over caller owned nodes. Unlike a binary
+/// heap, a queued node can change its key by being relinked.
+pub struct RbPriorityQueue<Key, T> {
+    tree: RbTreeCached<Key, T>,
+    len: usize,
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd> RbPriorityQueue<Key, T> {
+    pub fn new() -> Self {
+        RbPriorityQueue {
+            tree: RbTreeCached::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queues `node`, equal priorities are served first in first out.
+    pub fn push(&mut self, node: &mut RbNode<Key, T>) -> Result<(), RbError> {
+        self.tree.insert(node)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.tree.first().map(|n| &n.val)
+    }
+
+    pub fn pop(&mut self) -> Option<&mut RbNode<Key, T>> {
+        let node = self.tree.pop_first()?;
+        self.len -= 1;
+        Some(node)
+    }
+
+    /// Unqueues every node.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.len = 0;
+    }
+
+    /// Takes a queued `node` out of the queue.
+    pub fn remove(&mut self, node: &mut RbNode<Key, T>) -> Result<(), RbError> {
+        self.tree.delete(node)?;
+        self.len -= 1;
+        Ok(())
+    }
+
+    /// Moves a queued `node` to the place of its new `key`, which covers
+    /// decrease-key as well as increase-key.
+    pub fn update_key(&mut self, node: &mut RbNode<Key, T>, key: Key) -> Result<(), RbError> {
+        self.tree.delete(node)?;
+        node.set(key);
+        self.tree.insert(node)?;
+        Ok(())
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd> Default for RbPriorityQueue<Key, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::Test;
+
+    #[test]
+    fn decrease_key() {
+        let mut nodes: [RbNode<i32, Test>; 20] =
+            std::array::from_fn(|i| RbNode::<i32, Test>::new(100 + i as i32));
+        let mut queue = RbPriorityQueue::<i32, Test>::new();
+
+        assert!(queue.pop().is_none());
+        for node in nodes.iter_mut() {
+            queue.push(node).unwrap();
+        }
+        assert_eq!(queue.len(), 20);
+        assert_eq!(queue.peek().unwrap().get(), 100);
+
+        let [a, b, c, ..] = &mut nodes;
+        queue.update_key(c, 1).unwrap();
+        assert_eq!(queue.peek().unwrap().get(), 1);
+        queue.update_key(a, 500).unwrap();
+        queue.remove(b).unwrap();
+        assert_eq!(queue.len(), 19);
+
+        let order: Vec<i32> = std::iter::from_fn(|| queue.pop().map(|n| n.val().get())).collect();
+        let mut expect: Vec<i32> = (103..120).collect();
+        expect.insert(0, 1);
+        expect.push(500);
+        assert_eq!(order, expect);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{NodeColor, NodeDirection, RbAugment, RbNode, RbTree};
+use std::ptr::null_mut;
+
+// what the rebalancing cases need to know about a tree, so the pointer
+// linked RbTree and the index linked ArenaRbTree share one implementation;
+// set_child only writes the slot, the parent link of the child is left to
+// the caller
+pub(crate) trait RbLinks {
+    type Node: Copy + PartialEq;
+    const NIL: Self::Node;
+
+    fn root(&self) -> Self::Node;
+    fn set_root(&mut self, node: Self::Node);
+    fn parent(&self, node: Self::Node) -> Self::Node;
+    fn set_parent(&mut self, node: Self::Node, parent: Self::Node);
+    fn child(&self, node: Self::Node, di: NodeDirection) -> Self::Node;
+    fn set_child(&mut self, node: Self::Node, di: NodeDirection, child: Self::Node);
+    // NIL reads as black
+    fn color(&self, node: Self::Node) -> NodeColor;
+    fn set_color(&mut self, node: Self::Node, color: NodeColor);
+
+    // augment hooks, trees without summaries keep the no-ops
+    fn compute(&mut self, _node: Self::Node) {}
+    fn propagate(&mut self, _node: Self::Node, _stop: Self::Node) {}
+    fn rotated(&mut self, _old: Self::Node, _new: Self::Node) {}
+}
+
+impl<Key, T, A: RbAugment<Key, T>> RbLinks for RbTree<Key, T, A> {
+    type Node = *mut RbNode<Key, T>;
+    const NIL: Self::Node = null_mut();
+
+    fn root(&self) -> Self::Node {
+        self.root
+    }
+
+    fn set_root(&mut self, node: Self::Node) {
+        self.root = node
+    }
+
+    fn parent(&self, node: Self::Node) -> Self::Node {
+        unsafe { (*node).parent }
+    }
+
+    fn set_parent(&mut self, node: Self::Node, parent: Self::Node) {
+        unsafe { (*node).parent = parent }
+    }
+
+    fn child(&self, node: Self::Node, di: NodeDirection) -> Self::Node {
+        unsafe { (*node).childs[usize::from(di)] }
+    }
+
+    fn set_child(&mut self, node: Self::Node, di: NodeDirection, child: Self::Node) {
+        unsafe { (*node).childs[usize::from(di)] = child }
+    }
+
+    fn color(&self, node: Self::Node) -> NodeColor {
+        if node.is_null() {
+            NodeColor::Black
+        } else {
+            unsafe { (*node).color }
+        }
+    }
+
+    fn set_color(&mut self, node: Self::Node, color: NodeColor) {
+        unsafe { (*node).color = color }
+    }
+
+    fn compute(&mut self, node: Self::Node) {
+        unsafe { A::compute(&mut *node) };
+    }
+
+    fn propagate(&mut self, node: Self::Node, stop: Self::Node) {
+        unsafe { A::propagate(&mut *node, stop as *const RbNode<Key, T>) }
+    }
+
+    fn rotated(&mut self, old: Self::Node, new: Self::Node) {
+        unsafe { A::rotate(&mut *old, &mut *new) }
+    }
+}
+
+#[inline]
+fn direction<L: RbLinks>(l: &L, node: L::Node, parent: L::Node) -> NodeDirection {
+    if l.child(parent, NodeDirection::LeftChild) == node {
+        NodeDirection::LeftChild
+    } else {
+        NodeDirection::RightChild
+    }
+}
+
+// puts child below node towards di, child may be NIL
+#[inline]
+fn hook<L: RbLinks>(l: &mut L, node: L::Node, di: NodeDirection, child: L::Node) {
+    l.set_child(node, di, child);
+    if child != L::NIL {
+        l.set_parent(child, node);
+    }
+}
+
+// node takes the place, parent and color of old
+#[inline]
+fn inherit_parent<L: RbLinks>(l: &mut L, node: L::Node, old: L::Node) {
+    let parent = l.parent(old);
+    l.set_parent(node, parent);
+    l.set_color(node, l.color(old));
+    if parent == L::NIL {
+        l.set_root(node);
+    } else {
+        let di = direction(l, old, parent);
+        l.set_child(parent, di, node);
+    }
+}
+
+// node, the child of parent towards di, becomes the parent of parent, which
+// is painted color; hooking node above is left to inherit_parent
+#[inline]
+fn rotate_with_parent<L: RbLinks>(
+    l: &mut L,
+    node: L::Node,
+    parent: L::Node,
+    di: NodeDirection,
+    color: NodeColor,
+) {
+    let other = di.opposite();
+    let inner = l.child(node, other);
+    hook(l, parent, di, inner);
+    hook(l, node, other, parent);
+    l.set_color(parent, color);
+    l.rotated(parent, node);
+}
+
+// hooks node below parent towards di, or as the root when parent is NIL,
+// then restores the red black properties
+pub(crate) fn link<L: RbLinks>(l: &mut L, parent: L::Node, di: NodeDirection, node: L::Node) {
+    l.set_child(node, NodeDirection::LeftChild, L::NIL);
+    l.set_child(node, NodeDirection::RightChild, L::NIL);
+    l.compute(node);
+    if parent == L::NIL {
+        l.set_parent(node, L::NIL);
+        l.set_color(node, NodeColor::Black);
+        l.set_root(node);
+    } else {
+        l.set_color(node, NodeColor::Red);
+        hook(l, parent, di, node);
+        l.propagate(parent, L::NIL);
+        insert_fixup(l, node);
+    }
+}
+
+// returns whether the black height of the tree grew, which only happens
+// when a red node reaches the root
+pub(crate) fn insert_fixup<L: RbLinks>(l: &mut L, mut node: L::Node) -> bool {
+    loop {
+        let mut p = l.parent(node);
+        if p == L::NIL {
+            let grew = l.color(node) == NodeColor::Red;
+            l.set_color(node, NodeColor::Black);
+            l.set_root(node);
+            break grew;
+        }
+
+        if l.color(p) == NodeColor::Black {
+            break false;
+        }
+
+        let gp = l.parent(p);
+        let mut nd = direction(l, node, p);
+        let pd = direction(l, p, gp);
+        let uncle = l.child(gp, pd.opposite());
+        if l.color(uncle) == NodeColor::Red {
+            l.set_color(p, NodeColor::Black);
+            l.set_color(uncle, NodeColor::Black);
+            node = gp;
+            l.set_color(node, NodeColor::Red);
+            continue;
+        }
+
+        if nd != pd {
+            rotate_with_parent(l, node, p, nd, NodeColor::Red);
+            nd = pd;
+            p = node;
+        }
+
+        inherit_parent(l, p, gp);
+        rotate_with_parent(l, p, gp, nd, NodeColor::Red);
+        break false;
+    }
+}
+
+// takes node, known to be linked, out of the tree; its own links are left
+// to the caller
+pub(crate) fn unlink<L: RbLinks>(l: &mut L, node: L::Node) {
+    let left = l.child(node, NodeDirection::LeftChild);
+    let right = l.child(node, NodeDirection::RightChild);
+    let mut di = NodeDirection::LeftChild;
+
+    let mut fix = L::NIL;
+    // lowest node whose subtree lost a node, and the successor moved into
+    // the place of node if any
+    let mut changed = l.parent(node);
+    let mut moved = L::NIL;
+
+    if left == L::NIL {
+        if right != L::NIL {
+            inherit_parent(l, right, node);
+        } else {
+            let p = l.parent(node);
+            if p == L::NIL {
+                l.set_root(L::NIL);
+            } else {
+                di = direction(l, node, p);
+                l.set_child(p, di, L::NIL);
+                if l.color(node) == NodeColor::Black {
+                    fix = p;
+                }
+            }
+        }
+    } else if right == L::NIL {
+        inherit_parent(l, left, node);
+    } else {
+        let mut far_left = right;
+        while l.child(far_left, NodeDirection::LeftChild) != L::NIL {
+            far_left = l.child(far_left, NodeDirection::LeftChild);
+        }
+
+        let near_right = l.child(far_left, NodeDirection::RightChild);
+        let need_fix = l.color(far_left) == NodeColor::Black && near_right == L::NIL;
+        moved = far_left;
+        if far_left != right {
+            fix = l.parent(far_left);
+            changed = fix;
+            hook(l, fix, NodeDirection::LeftChild, near_right);
+            if near_right != L::NIL {
+                l.set_color(near_right, NodeColor::Black);
+            }
+            hook(l, far_left, NodeDirection::RightChild, right);
+        } else {
+            fix = far_left;
+            changed = far_left;
+            di = NodeDirection::RightChild;
+            if near_right != L::NIL {
+                l.set_color(near_right, NodeColor::Black);
+            }
+        }
+
+        if !need_fix {
+            fix = L::NIL;
+        }
+
+        inherit_parent(l, far_left, node);
+        hook(l, far_left, NodeDirection::LeftChild, left);
+    }
+
+    // summaries have to be right again before rebalancing rotates
+    if moved != L::NIL {
+        l.propagate(changed, moved);
+        l.compute(moved);
+        changed = l.parent(moved);
+    }
+    if changed != L::NIL {
+        l.propagate(changed, L::NIL);
+    }
+
+    if fix != L::NIL {
+        delete_fixup(l, fix, di);
+    }
+}
+
+// the subtree of parent towards nd is one black node short
+fn delete_fixup<L: RbLinks>(l: &mut L, mut parent: L::Node, mut nd: NodeDirection) {
+    let node = loop {
+        let sd = nd.opposite();
+        let mut s = l.child(parent, sd);
+
+        if l.color(s) == NodeColor::Red {
+            inherit_parent(l, s, parent);
+            rotate_with_parent(l, s, parent, sd, NodeColor::Red);
+            s = l.child(parent, sd);
+        }
+
+        let sc = [
+            l.child(s, NodeDirection::LeftChild),
+            l.child(s, NodeDirection::RightChild),
+        ];
+        let scc = [l.color(sc[0]), l.color(sc[1])];
+
+        if scc == [NodeColor::Black, NodeColor::Black] {
+            l.set_color(s, NodeColor::Red);
+            if parent != l.root() && l.color(parent) == NodeColor::Black {
+                let gp = l.parent(parent);
+                nd = direction(l, parent, gp);
+                parent = gp;
+                continue;
+            }
+            break parent;
+        }
+
+        if scc[usize::from(sd)] == NodeColor::Black {
+            let inner = sc[usize::from(nd)];
+            inherit_parent(l, inner, s);
+            rotate_with_parent(l, inner, s, nd, NodeColor::Red);
+            s = l.parent(s);
+        }
+
+        inherit_parent(l, s, parent);
+        rotate_with_parent(l, s, parent, sd, NodeColor::Black);
+        let outer = l.child(s, sd);
+        if outer != L::NIL {
+            l.set_color(outer, NodeColor::Black);
+        }
+        break l.root();
+    };
+    l.set_color(node, NodeColor::Black);
+}
@@ -0,0 +1,114 @@
+use crate::map::{self, RbMap};
+
+/// An ordered set owning its nodes, a thin wrapper over [`RbMap`].
+pub struct RbSet<T> {
+    map: RbMap<T, ()>,
+}
+
+impl<T: Ord + Clone> RbSet<T> {
+    pub fn new() -> Self {
+        RbSet { map: RbMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+
+    /// Returns whether `value` was newly inserted.
+    pub fn insert(&mut self, value: T) -> bool {
+        match self.map.entry(value) {
+            map::Entry::Occupied(_) => false,
+            map::Entry::Vacant(entry) => {
+                entry.insert(());
+                true
+            }
+        }
+    }
+
+    /// Returns whether `value` was present.
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.map.remove(value).is_some()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.map.first_key_value().map(|(k, _)| k)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.map.last_key_value().map(|(k, _)| k)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<T: Ord + Clone> Default for RbSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Ord + Clone> IntoIterator for &'a RbSet<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    inner: map::Iter<'a, T, ()>,
+}
+
+impl<'a, T: Ord + Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next().map(|(k, _)| k)
+    }
+}
+
+impl<'a, T: Ord + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_remove() {
+        let mut set = RbSet::<i32>::new();
+        for i in (0..100).rev() {
+            assert_eq!(set.insert(i % 50), i >= 50);
+        }
+        assert_eq!(set.len(), 50);
+        assert!(set.iter().copied().eq(0..50));
+
+        for i in (0..50).step_by(2) {
+            assert!(set.remove(&i));
+            assert!(!set.remove(&i));
+        }
+        assert!(set.iter().copied().eq((1..50).step_by(2)));
+        assert_eq!(set.first(), Some(&1));
+        assert_eq!(set.last(), Some(&49));
+    }
+}
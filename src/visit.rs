@@ -0,0 +1,267 @@
+use crate::{NodeDirection, RbAugment, RbNode, RbTrait, RbTree};
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::ptr::null_mut;
+
+// the walks below climb the parent links instead of keeping a stack, so the
+// depth of the tree never matters
+impl<Key, T: RbTrait<Key> + PartialOrd> RbNode<Key, T> {
+    // the node after node in preorder, null past the last one
+    fn next_preorder(mut node: *mut RbNode<Key, T>) -> *mut RbNode<Key, T> {
+        unsafe {
+            for di in [NodeDirection::LeftChild, NodeDirection::RightChild] {
+                if !(*node).get_child(di).is_null() {
+                    return (*node).get_child(di);
+                }
+            }
+
+            // up to the first ancestor whose right subtree is not walked yet
+            let mut parent = (*node).parent;
+            while !parent.is_null() {
+                let right = (*parent).get_child(NodeDirection::RightChild);
+                if !right.is_null() && right != node {
+                    return right;
+                }
+                node = parent;
+                parent = (*node).parent;
+            }
+            null_mut()
+        }
+    }
+}
+
+// no bounds here, the drop of an owning wrapper tears its tree down with these
+impl<Key, T> RbNode<Key, T> {
+    // the first node in postorder below node, the leftmost leaf
+    pub(crate) fn first_postorder(mut node: *mut RbNode<Key, T>) -> *mut RbNode<Key, T> {
+        unsafe {
+            while !node.is_null() {
+                let left = (*node).childs[0];
+                let next = if left.is_null() {
+                    (*node).childs[1]
+                } else {
+                    left
+                };
+                if next.is_null() {
+                    break;
+                }
+                node = next;
+            }
+        }
+        node
+    }
+
+    // the node after node in postorder, null past the root
+    pub(crate) fn next_postorder(node: *mut RbNode<Key, T>) -> *mut RbNode<Key, T> {
+        unsafe {
+            let parent = (*node).parent;
+            if parent.is_null() {
+                return null_mut();
+            }
+
+            let right = (*parent).childs[1];
+            if right.is_null() || right == node {
+                parent
+            } else {
+                Self::first_postorder(right)
+            }
+        }
+    }
+}
+
+impl<Key, T, A> RbTree<Key, T, A> {
+    /// Unlinks every node and hands it to `f` after its children, so `f`
+    /// may free it. The tree is left empty, in O(n) and without rebalancing.
+    pub fn drain_postorder<F: FnMut(*mut RbNode<Key, T>)>(&mut self, mut f: F) {
+        let mut node = RbNode::first_postorder(self.root);
+        self.root = null_mut();
+        while !node.is_null() {
+            // only the address of node is looked at once it is unlinked
+            let next = RbNode::next_postorder(node);
+            unsafe { (*node).clear_links() };
+            f(node);
+            node = next;
+        }
+    }
+
+    /// Unlinks every node, leaving the tree empty. Dropping a tree leaves
+    /// its nodes linked, clear it first when they are to be linked again.
+    pub fn clear(&mut self) {
+        self.drain_postorder(|_| {});
+    }
+}
+
+impl<Key, T: RbTrait<Key> + PartialOrd, A: RbAugment<Key, T>> RbTree<Key, T, A> {
+    fn walk<B, F: FnMut(&T) -> ControlFlow<B>>(
+        mut node: *mut RbNode<Key, T>,
+        next: fn(*mut RbNode<Key, T>) -> *mut RbNode<Key, T>,
+        mut f: F,
+    ) -> ControlFlow<B> {
+        while !node.is_null() {
+            f(unsafe { &(*node).val })?;
+            node = next(node);
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Calls `f` on each value, parents before their children, until it
+    /// breaks.
+    pub fn visit_preorder<B, F: FnMut(&T) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        Self::walk(self.root, RbNode::next_preorder, f)
+    }
+
+    /// Calls `f` on each value in sorted order until it breaks.
+    pub fn visit_inorder<B, F: FnMut(&T) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        Self::walk(
+            RbNode::extreme(self.root, NodeDirection::LeftChild),
+            |node| RbNode::neighbour(node, NodeDirection::RightChild),
+            f,
+        )
+    }
+
+    /// Calls `f` on each value, children before their parents, until it
+    /// breaks.
+    pub fn visit_postorder<B, F: FnMut(&T) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        Self::walk(
+            RbNode::first_postorder(self.root),
+            RbNode::next_postorder,
+            f,
+        )
+    }
+
+    /// Calls `f` on each value level by level from the root, left to right,
+    /// until it breaks.
+    pub fn visit_levelorder<B, F: FnMut(&T) -> ControlFlow<B>>(&self, mut f: F) -> ControlFlow<B> {
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(node) = queue.pop_front() {
+            if let Some(node) = unsafe { node.as_ref() } {
+                f(&node.val)?;
+                queue.extend(node.childs);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Test;
+    use crate::utils;
+    use crate::{RbNode, RbTrait, RbTree};
+    use std::ops::ControlFlow;
+
+    // the same orders taken recursively from the links
+    fn recurse(node: Option<&RbNode<i32, Test>>, order: usize, out: &mut Vec<i32>) {
+        if let Some(node) = node {
+            if order == 0 {
+                out.push(node.val().get());
+            }
+            recurse(node.left(), order, out);
+            if order == 1 {
+                out.push(node.val().get());
+            }
+            recurse(node.right(), order, out);
+            if order == 2 {
+                out.push(node.val().get());
+            }
+        }
+    }
+
+    #[test]
+    fn visitors() {
+        let mut array = [0i32; 200];
+        let mut nodes: [RbNode<i32, Test>; 200] =
+            std::array::from_fn(|_| RbNode::<i32, Test>::new(0));
+        let mut tree = RbTree::<i32, Test>::new();
+
+        let _ = utils::read_blocks_from_file::<i32>("/dev/urandom", &mut array, 200);
+        for (node, key) in nodes.iter_mut().zip(array.iter()) {
+            node.set(*key);
+            tree.insert(node).unwrap();
+        }
+
+        let root = unsafe { tree.root.as_ref() };
+        type Visit = fn(&RbTree<i32, Test>, &mut dyn FnMut(&Test) -> ControlFlow<()>);
+        let visits: [Visit; 3] = [
+            |t, f| {
+                let _ = t.visit_preorder(f);
+            },
+            |t, f| {
+                let _ = t.visit_inorder(f);
+            },
+            |t, f| {
+                let _ = t.visit_postorder(f);
+            },
+        ];
+        for (order, visit) in visits.iter().enumerate() {
+            let mut want = Vec::new();
+            recurse(root, order, &mut want);
+            let mut got = Vec::new();
+            visit(&tree, &mut |v| {
+                got.push(v.get());
+                ControlFlow::Continue(())
+            });
+            assert_eq!(got, want);
+        }
+
+        // level order, each level holding the children of the one before
+        let mut got = Vec::new();
+        let _ = tree.visit_levelorder(|v| {
+            got.push(v.get());
+            ControlFlow::<()>::Continue(())
+        });
+        let mut want = Vec::new();
+        let mut level = vec![root.unwrap()];
+        while !level.is_empty() {
+            want.extend(level.iter().map(|n| n.val().get()));
+            level = level
+                .iter()
+                .flat_map(|n| [n.left(), n.right()])
+                .flatten()
+                .collect();
+        }
+        assert_eq!(got, want);
+
+        // stopping early hands back the break value
+        let mut seen = 0;
+        let found = tree.visit_inorder(|v| {
+            seen += 1;
+            if seen == 10 {
+                ControlFlow::Break(v.get())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(found, ControlFlow::Break(tree.iter().nth(9).unwrap().get()));
+        assert_eq!(seen, 10);
+    }
+
+    #[test]
+    fn drain() {
+        let mut nodes: Vec<RbNode<i32, Test>> = (0..50).map(RbNode::new).collect();
+        let mut tree = RbTree::<i32, Test>::new();
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+
+        let mut want = Vec::new();
+        let _ = tree.visit_postorder(|v| {
+            want.push(v.get());
+            ControlFlow::<()>::Continue(())
+        });
+        let mut got = Vec::new();
+        tree.drain_postorder(|node| {
+            got.push(unsafe { (*node).val().get() });
+            // scribble over the node as a free would
+            unsafe { (*node).childs = [std::ptr::null_mut(); 2] };
+        });
+        assert_eq!(got, want);
+        assert!(tree.first().is_none());
+
+        for node in nodes.iter_mut() {
+            tree.insert(node).unwrap();
+        }
+        tree.clear();
+        assert!(tree.iter().next().is_none());
+    }
+}